@@ -1,17 +1,36 @@
-use log::trace;
+use chrono::Local;
+use log::{debug, error, info, trace};
 use ratatui::{prelude::*, widgets::*};
-use std::io::{Seek, SeekFrom};
-use std::{fs::metadata, io, time::Duration};
-use std::{fs::File, io::Read};
-
-use crossterm::event::{self, KeyEvent};
-use crossterm::event::{Event, KeyCode, KeyEventKind};
-
-use tui_textarea::TextArea;
-
+use std::io;
+use std::path::PathBuf;
+
+use crossterm::event::KeyEvent;
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+
+use tui_textarea::{CursorMove, TextArea};
+
+use crate::config::Config;
+use crate::editor::{self, FileRef};
+use crate::events::{AppEvent, Events};
+use crate::highlight::{self, Highlighter};
+use crate::level::{Level, LevelStyles};
+use crate::log_fields::Field;
 use crate::log_line::LogData;
+use crate::pager::Pager;
+use crate::query::Query;
+use crate::raw_parse::RawParser;
+use crate::search::{regex_error_reason, Search, MATCH_COLOR};
+use crate::stack_trace::StackTrace;
 use crate::stateful_list::StatefulList;
+use crate::time_range::TimeRange;
+use crate::trace_view::TraceView;
 use crate::ui::{self, make_title};
+use crate::view_state::{PaneLayout, ViewState};
+use crate::watcher::{FileEvent, FileWatcher};
 
 #[derive(Debug)]
 pub struct KeyBinding {
@@ -39,12 +58,31 @@ pub enum Command {
     ListUp,
     ListDown,
     Unselect,
+    ToggleStackTrace,
+    ToggleVendorFrames,
+    OpenInEditor,
+    ToggleLevel,
+    CycleMinLevel,
+    ShowAllLevels,
+    ResetFilter,
+    TimeRange,
+    Search,
+    NextSearchHit,
+    PreviousSearchHit,
+    SearchContent,
+    ToggleWrap,
+    Select,
+    ToggleHighlight,
+    CycleLayout,
+    ResizeList,
 }
 
 #[derive(Debug)]
 pub struct FileInfo {
     pub name: String,
     pub size: u64,
+    /// The file was removed, waiting for it to come back
+    pub missing: bool,
 }
 
 // struct App<'a> {
@@ -57,7 +95,7 @@ pub struct App<'a> {
     /// Should scroll as new logs come in
     follow_mode: bool,
 
-    /// List of regex filters
+    /// List of filter queries, one per line in the filter popup
     filter: Option<Vec<String>>,
 
     /// Current app state
@@ -69,7 +107,38 @@ pub struct App<'a> {
     keybindings_state: TableState,
 
     textarea: TextArea<'a>,
-    log_textarea: Option<TextArea<'a>>,
+    time_textarea: TextArea<'a>,
+    search_textarea: TextArea<'a>,
+    content_search_textarea: TextArea<'a>,
+    /// Read only view of the selected log
+    log_pager: Option<Pager>,
+    /// Context and extra of the selected log
+    log_fields: Vec<Field>,
+    /// Stack trace of the selected log, if it has one
+    trace_view: Option<TraceView>,
+    /// Show the stack trace instead of the raw text when there is one
+    show_trace: bool,
+
+    /// Highlight rules from the config
+    highlighter: Highlighter,
+    /// List colors per level
+    level_styles: LevelStyles,
+
+    config: Config,
+    /// File to open in $EDITOR once the terminal is suspended
+    open_in_editor: Option<FileRef>,
+
+    /// Layout of the panes, saved to `view_path` on exit
+    view: ViewState,
+    view_path: Option<PathBuf>,
+
+    /// Poll the log file instead of waiting for filesystem notifications
+    poll_file: bool,
+
+    /// Something changed since the last draw
+    dirty: bool,
+    /// Number of draws, logged to see how often we redraw
+    redraws: u64,
 
     /// Should exit
     exit: bool,
@@ -80,6 +149,9 @@ enum AppMode {
     Normal,
     FocusLogText,
     EditingFilter,
+    EditingTimeRange,
+    EditingSearch,
+    EditingContentSearch,
     ShowingKeybindings,
 }
 
@@ -92,9 +164,25 @@ enum AppMode {
 
 // impl<'a> App<'a> {
 impl<'a> App<'a> {
-    pub fn new(file: FileInfo, log_data: LogData) -> App<'a> {
+    /// The pane layout is loaded from `view_path` and saved there on exit
+    pub fn new(
+        file: FileInfo,
+        parser: RawParser,
+        log_data: LogData,
+        config: Config,
+        view_path: Option<PathBuf>,
+    ) -> App<'a> {
         let mut textarea = TextArea::default();
-        textarea.set_block(Block::default().title("Filter").borders(Borders::ALL));
+        textarea.set_block(prompt_block("Filter", None));
+
+        let mut time_textarea = TextArea::default();
+        time_textarea.set_block(prompt_block("Time range", None));
+
+        let mut search_textarea = TextArea::default();
+        search_textarea.set_block(prompt_block("Search", None));
+
+        let mut content_search_textarea = TextArea::default();
+        content_search_textarea.set_block(prompt_block("Search in content", None));
 
         let keybindings = vec![
             KeyBinding::new(
@@ -104,14 +192,99 @@ impl<'a> App<'a> {
             ),
             KeyBinding::new(
                 KeyCode::Char('/'),
-                "Filter on things".to_owned(),
+                "Filter query, e.g. level>=warning text~webhook. Alt+Enter for new line".to_owned(),
                 Command::Filter,
             ),
+            KeyBinding::new(
+                KeyCode::Char('r'),
+                "Reset filter".to_owned(),
+                Command::ResetFilter,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('T'),
+                "Time range, e.g. 15m, last 2 hours or 2023-02-14 13:00..14:00".to_owned(),
+                Command::TimeRange,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('s'),
+                "Search, matching logs are highlighted but not filtered".to_owned(),
+                Command::Search,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('n'),
+                "Next search hit".to_owned(),
+                Command::NextSearchHit,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('N'),
+                "Previous search hit".to_owned(),
+                Command::PreviousSearchHit,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('/'),
+                "In content: search the selected log, n/N for next/previous hit".to_owned(),
+                Command::SearchContent,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('w'),
+                "In content: toggle line wrap".to_owned(),
+                Command::ToggleWrap,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('v'),
+                "In content: start or stop selecting, y to copy the selection".to_owned(),
+                Command::Select,
+            ),
             KeyBinding::new(
                 KeyCode::Char('q'),
                 "Exit application".to_owned(),
                 Command::Quit,
             ),
+            KeyBinding::new(
+                KeyCode::Char('1'),
+                "Toggle levels, 1-8 for DEBUG..EMERGENCY".to_owned(),
+                Command::ToggleLevel,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('m'),
+                "Cycle minimum level".to_owned(),
+                Command::CycleMinLevel,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('0'),
+                "Show all levels".to_owned(),
+                Command::ShowAllLevels,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('h'),
+                "Toggle highlight rules from the config".to_owned(),
+                Command::ToggleHighlight,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('l'),
+                "Cycle layout: side by side, stacked, list only, content only".to_owned(),
+                Command::CycleLayout,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('+'),
+                "Grow the list, - to shrink it, when both panes are shown".to_owned(),
+                Command::ResizeList,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('t'),
+                "Toggle stack trace view".to_owned(),
+                Command::ToggleStackTrace,
+            ),
+            KeyBinding::new(
+                KeyCode::Char('v'),
+                "Collapse vendor frames in stack trace".to_owned(),
+                Command::ToggleVendorFrames,
+            ),
+            KeyBinding::new(
+                KeyCode::Enter,
+                "Open file:line under cursor in $EDITOR".to_owned(),
+                Command::OpenInEditor,
+            ),
             KeyBinding::new(KeyCode::Up, "Move list up".to_owned(), Command::ListUp),
             KeyBinding::new(
                 KeyCode::Down,
@@ -120,10 +293,15 @@ impl<'a> App<'a> {
             ),
         ];
 
+        let view = view_path
+            .as_deref()
+            .map(ViewState::load)
+            .unwrap_or_default();
+
         App {
             size: Rect::default(),
             file,
-            list_items: StatefulList::with_items(log_data),
+            list_items: StatefulList::with_items(parser, log_data),
 
             follow_mode: false,
 
@@ -135,48 +313,132 @@ impl<'a> App<'a> {
             keybindings_state: TableState::default(),
 
             textarea,
-            log_textarea: None,
+            time_textarea,
+            search_textarea,
+            content_search_textarea,
+            log_pager: None,
+            log_fields: Vec::new(),
+            trace_view: None,
+            show_trace: true,
+
+            highlighter: Highlighter::new(&config.highlight),
+            level_styles: LevelStyles::new(&config.levels),
+            config,
+            open_in_editor: None,
+
+            view,
+            view_path,
+
+            poll_file: false,
+
+            dirty: true,
+            redraws: 0,
 
             exit: false,
         }
     }
 
     pub fn run_app<B: Backend>(mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        let events = Events::new();
+        FileWatcher::new(&self.file.name, self.file.size).spawn(events.sender(), self.poll_file);
+
         while !self.exit {
             if self.follow_mode {
                 self.list_items.goto_end();
             }
 
-            terminal.draw(|f| self.ui(f))?;
+            if self.dirty {
+                terminal.draw(|f| self.ui(f))?;
+                self.dirty = false;
+                self.redraws += 1;
+                debug!("Redraw #{}", self.redraws);
+            }
 
-            self.handle_events()?;
+            // Handle everything that piled up during the draw before drawing again
+            self.handle_event(events.next()?)?;
+            while let Some(event) = events.try_next() {
+                if self.exit {
+                    break;
+                }
+                self.handle_event(event)?;
+            }
 
-            self.listen_file_notification()?;
+            if let Some(file_ref) = self.open_in_editor.take() {
+                self.run_editor(terminal, file_ref)?;
+                self.dirty = true;
+            }
+            events.resume_input();
+        }
 
-            // trace!("Loop!");
+        info!("Exit after {} redraws", self.redraws);
+        if let Some(path) = &self.view_path {
+            if let Err(err) = self.view.save(path) {
+                error!("Failed to save view state to {}: {}", path.display(), err);
+            }
         }
 
         Ok(())
     }
 
-    fn handle_events(&mut self) -> io::Result<()> {
-        if event::poll(Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                // Global key commands
-                match key.code {
-                    KeyCode::Char('q') => {
-                        self.exit = true;
-                        return Ok(());
-                    }
-                    _ => {}
-                }
+    /// Suspend the TUI while the editor runs, then restore it
+    fn run_editor<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        file_ref: FileRef,
+    ) -> io::Result<()> {
+        let path = self.config.map_path(&file_ref.path);
+        trace!("Open {:?}:{} in editor", path, file_ref.line);
 
-                match self.app_mode {
-                    AppMode::Normal => self.handle_events_log_list(key)?,
-                    AppMode::FocusLogText => self.handle_events_log_text(key)?,
-                    AppMode::EditingFilter => self.handle_events_filter(key)?,
-                    AppMode::ShowingKeybindings => self.handle_events_show_keybindings(key)?,
-                }
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen)?;
+
+        if let Err(err) = editor::open(&path, file_ref.line) {
+            error!("Failed to open {:?} in editor: {}", path, err);
+        }
+
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        enable_raw_mode()?;
+        terminal.clear()?;
+
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: AppEvent) -> io::Result<()> {
+        match event {
+            AppEvent::Input(event) => self.handle_input(event)?,
+            AppEvent::File(event) => self.handle_file_event(event),
+        }
+
+        Ok(())
+    }
+
+    fn handle_input(&mut self, event: Event) -> io::Result<()> {
+        if matches!(event, Event::Key(_) | Event::Resize(_, _)) {
+            self.dirty = true;
+        }
+
+        if let Event::Key(key) = event {
+            // Global key commands
+            let editing = matches!(
+                self.app_mode,
+                AppMode::EditingFilter
+                    | AppMode::EditingTimeRange
+                    | AppMode::EditingSearch
+                    | AppMode::EditingContentSearch
+            );
+            if key.code == KeyCode::Char('q') && !editing {
+                self.exit = true;
+                return Ok(());
+            }
+
+            match self.app_mode {
+                AppMode::Normal => self.handle_events_log_list(key)?,
+                AppMode::FocusLogText => self.handle_events_log_text(key)?,
+                AppMode::EditingFilter => self.handle_events_filter(key)?,
+                AppMode::EditingTimeRange => self.handle_events_time_range(key)?,
+                AppMode::EditingSearch => self.handle_events_search(key)?,
+                AppMode::EditingContentSearch => self.handle_events_content_search(key)?,
+                AppMode::ShowingKeybindings => self.handle_events_show_keybindings(key)?,
             }
         }
 
@@ -184,19 +446,34 @@ impl<'a> App<'a> {
     }
 
     fn handle_events_log_list(&mut self, key: KeyEvent) -> io::Result<()> {
+        let selected = self.list_items.selected_log_ix();
+
         match key.code {
             KeyCode::Char('f') => self.follow_mode = !self.follow_mode,
-            KeyCode::Char('c') => {
-                self.list_items.clear_all();
-                self.log_textarea = None;
-            }
+            KeyCode::Char('c') => self.list_items.clear_all(),
             KeyCode::Char('x') => self.list_items.set_cutoff(0),
+            KeyCode::Char(c @ '1'..='8') => {
+                let level = Level::ALL[c as usize - '1' as usize];
+                self.list_items.toggle_level(level);
+            }
+            KeyCode::Char('m') => self.list_items.cycle_min_level(),
+            KeyCode::Char('0') => self.list_items.show_all_levels(),
+            KeyCode::Char('r') => self.reset_filter(),
+            KeyCode::Char('h') => self.highlighter.toggle(),
+            KeyCode::Char('l') => self.view.cycle_layout(),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.view.grow_list(),
+            KeyCode::Char('-') => self.view.shrink_list(),
+            KeyCode::Char('T') => self.app_mode = AppMode::EditingTimeRange,
+            KeyCode::Char('s') => self.app_mode = AppMode::EditingSearch,
+            KeyCode::Char('n') => self.list_items.next_search_hit(),
+            KeyCode::Char('N') => self.list_items.previous_search_hit(),
             KeyCode::Char('?') => self.app_mode = AppMode::ShowingKeybindings,
             KeyCode::Char('/') => self.app_mode = AppMode::EditingFilter,
-            KeyCode::Tab => {
-                if self.list_items.selected_item().is_some() {
-                    self.app_mode = AppMode::FocusLogText;
-                }
+            KeyCode::Char('t') => self.show_trace = !self.show_trace,
+            KeyCode::Tab
+                if self.view.shows_content() && self.list_items.selected_item().is_some() =>
+            {
+                self.app_mode = AppMode::FocusLogText;
             }
 
             KeyCode::PageUp => self
@@ -217,20 +494,77 @@ impl<'a> App<'a> {
             _ => {}
         }
 
-        self.update_logtext();
+        // Rebuilding the content resets the pager, only do it for a new selection
+        if self.list_items.selected_log_ix() != selected {
+            self.update_logtext();
+        }
 
         Ok(())
     }
 
     fn handle_events_log_text(&mut self, key: KeyEvent) -> io::Result<()> {
         if key.kind == KeyEventKind::Press {
+            let page = (self.size.height / 3) as isize;
+            if key.code == KeyCode::Char('t') && self.trace_view.is_some() {
+                self.show_trace = !self.show_trace;
+                return Ok(());
+            }
+
+            // Search works on the text, leave the trace view for it
+            if matches!(key.code, KeyCode::Char('/' | 'n' | 'N')) && self.log_pager.is_some() {
+                self.show_trace = false;
+            }
+
+            if let Some(trace_view) = self.visible_trace_view() {
+                match key.code {
+                    KeyCode::Up => trace_view.previous(),
+                    KeyCode::Down => trace_view.next(),
+                    KeyCode::PageUp => trace_view.jump_relative(-page),
+                    KeyCode::PageDown => trace_view.jump_relative(page),
+                    KeyCode::Home => trace_view.goto_start(),
+                    KeyCode::End => trace_view.goto_end(),
+                    KeyCode::Char('v') => trace_view.toggle_vendor(),
+                    KeyCode::Enter => self.open_in_editor = trace_view.selected_file_ref(),
+                    KeyCode::Esc => self.app_mode = AppMode::Normal,
+                    _ => {}
+                }
+
+                return Ok(());
+            }
+
+            let Some(pager) = &mut self.log_pager else {
+                if key.code == KeyCode::Esc {
+                    self.app_mode = AppMode::Normal;
+                }
+                return Ok(());
+            };
+
             match key.code {
-                KeyCode::Esc => self.app_mode = AppMode::Normal,
-                _ => {
-                    if let Some(text_area) = &mut self.log_textarea {
-                        text_area.input(key);
+                KeyCode::Esc => {
+                    pager.clear_selection();
+                    self.app_mode = AppMode::Normal;
+                }
+                KeyCode::Char('/') => self.app_mode = AppMode::EditingContentSearch,
+                KeyCode::Char('n') => {
+                    pager.search_forward(false);
+                }
+                KeyCode::Char('N') => {
+                    pager.search_back();
+                }
+                KeyCode::Char('y') => {
+                    if let Some(text) = pager.selected_text() {
+                        ui::copy_to_clipboard(&text)?;
+                        pager.clear_selection();
                     }
                 }
+                KeyCode::Enter => {
+                    let (row, col) = pager.cursor();
+                    let line = pager.lines().get(row).map_or("", |l| l.as_str());
+                    self.open_in_editor = editor::file_ref_at(line, col);
+                }
+                _ => {
+                    pager.input(key);
+                }
             }
         }
 
@@ -239,14 +573,15 @@ impl<'a> App<'a> {
 
     fn handle_events_filter(&mut self, key: KeyEvent) -> io::Result<()> {
         if key.kind == KeyEventKind::Press {
+            let new_line = key
+                .modifiers
+                .intersects(KeyModifiers::ALT | KeyModifiers::SHIFT);
+
             match key.code {
-                // KeyCode::Enter => {
-                //     self.filter = Some(self.textarea.lines().iter().cloned().collect());
-                //     trace!("Filter: {:?}", self.filter);
-                //
-                //     self.app_mode = AppMode::Normal;
-                //     self.hide_popups()
-                // }
+                KeyCode::Enter if new_line => {
+                    self.textarea.insert_newline();
+                }
+                KeyCode::Enter => self.apply_filter(),
                 KeyCode::Esc => {
                     trace!("Input: {:?}", self.filter);
 
@@ -262,6 +597,177 @@ impl<'a> App<'a> {
         Ok(())
     }
 
+    /// Parse each line in the filter popup as a query and filter the list on them
+    fn apply_filter(&mut self) {
+        let mut filters = Vec::new();
+        let mut lines = Vec::new();
+        for (row, line) in self.textarea.lines().iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match Query::parse(line) {
+                Ok(query) => {
+                    filters.push(query);
+                    lines.push(line.clone());
+                }
+                Err(err) => {
+                    let col = line[..err.pos].chars().count();
+                    let error = format!("line {}, col {}: {}", row + 1, col + 1, err);
+
+                    self.textarea.set_block(prompt_block("Filter", Some(error)));
+                    self.textarea
+                        .move_cursor(CursorMove::Jump(row as u16, col as u16));
+                    return;
+                }
+            }
+        }
+
+        trace!("Filter: {:?}", lines);
+        self.filter = if lines.is_empty() { None } else { Some(lines) };
+        self.list_items.set_filters(filters);
+        self.textarea.set_block(prompt_block("Filter", None));
+
+        self.hide_popups();
+        self.update_logtext();
+    }
+
+    fn reset_filter(&mut self) {
+        self.filter = None;
+        self.list_items.set_filters(Vec::new());
+        self.textarea = TextArea::default();
+        self.textarea.set_block(prompt_block("Filter", None));
+    }
+
+    fn handle_events_time_range(&mut self, key: KeyEvent) -> io::Result<()> {
+        if key.kind == KeyEventKind::Press {
+            match key.code {
+                KeyCode::Enter => self.apply_time_range(),
+                KeyCode::Esc => self.hide_popups(),
+                _ => {
+                    self.time_textarea.input(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_time_range(&mut self) {
+        let expr = self.time_textarea.lines().join(" ");
+        let range = if expr.trim().is_empty() {
+            Ok(TimeRange::default())
+        } else {
+            TimeRange::parse(&expr, Local::now())
+        };
+
+        match range {
+            Ok(range) => {
+                trace!("Time range: {:?}", range);
+                self.time_textarea
+                    .set_block(prompt_block("Time range", None));
+                self.set_time_range(range);
+                self.hide_popups();
+            }
+            Err(err) => self
+                .time_textarea
+                .set_block(prompt_block("Time range", Some(err))),
+        }
+    }
+
+    pub fn set_poll_file(&mut self, poll: bool) {
+        self.poll_file = poll;
+    }
+
+    pub fn set_time_range(&mut self, range: TimeRange) {
+        self.list_items.set_time_range(range);
+        self.update_logtext();
+    }
+
+    fn handle_events_search(&mut self, key: KeyEvent) -> io::Result<()> {
+        if key.kind == KeyEventKind::Press {
+            match key.code {
+                KeyCode::Enter => self.apply_search(),
+                KeyCode::Esc => self.hide_popups(),
+                _ => {
+                    self.search_textarea.input(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Search for the pattern in the prompt and jump to the first hit after the selection
+    fn apply_search(&mut self) {
+        let pattern = self.search_textarea.lines().join("");
+        let search = if pattern.is_empty() {
+            Ok(None)
+        } else {
+            Search::new(&pattern).map(Some)
+        };
+
+        match search {
+            Ok(search) => {
+                trace!("Search: {:?}", search);
+                self.search_textarea.set_block(prompt_block("Search", None));
+                self.list_items.set_search(search);
+                self.list_items.next_search_hit();
+                self.hide_popups();
+                self.update_logtext();
+            }
+            Err(err) => {
+                let error = regex_error_reason(&err);
+                self.search_textarea
+                    .set_block(prompt_block("Search", Some(error)));
+            }
+        }
+    }
+
+    fn handle_events_content_search(&mut self, key: KeyEvent) -> io::Result<()> {
+        if key.kind == KeyEventKind::Press {
+            match key.code {
+                KeyCode::Enter => self.apply_content_search(),
+                KeyCode::Esc => self.app_mode = AppMode::FocusLogText,
+                _ => {
+                    self.content_search_textarea.input(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Search the selected log and move the cursor to the first hit
+    fn apply_content_search(&mut self) {
+        let pattern = self.content_search_textarea.lines().join("");
+        let search = if pattern.is_empty() {
+            Ok(None)
+        } else {
+            Search::new(&pattern).map(Some)
+        };
+
+        match search {
+            Ok(search) => {
+                self.content_search_textarea
+                    .set_block(prompt_block("Search in content", None));
+
+                // An empty search goes back to highlighting the list search
+                let search = search.or_else(|| self.list_items.search().cloned());
+                if let Some(pager) = &mut self.log_pager {
+                    pager.set_search(search);
+                    pager.search_forward(true);
+                }
+                self.app_mode = AppMode::FocusLogText;
+            }
+            Err(err) => {
+                let error = regex_error_reason(&err);
+                self.content_search_textarea
+                    .set_block(prompt_block("Search in content", Some(error)));
+            }
+        }
+    }
+
     fn handle_events_show_keybindings(&mut self, key: KeyEvent) -> io::Result<()> {
         match key.code {
             KeyCode::Up => {
@@ -294,51 +800,66 @@ impl<'a> App<'a> {
         Ok(())
     }
 
-    fn listen_file_notification(&mut self) -> io::Result<()> {
-        // Handle notify events here
-        if let Ok(meta) = metadata(&self.file.name) {
-            if self.file.size != meta.len() {
-                let mut file = File::open(&self.file.name)?;
-
-                if self.file.size < meta.len() {
-                    file.seek(SeekFrom::Start(self.file.size)).unwrap();
-                }
-
-                let mut contents = String::new();
-                file.read_to_string(&mut contents)?;
-
-                if self.file.size < meta.len() {
-                    trace!("File size increased {:?}", contents);
-                    self.list_items.append_text(&contents);
-                } else {
-                    trace!("File size reduced {:?} to {:?}", self.file.size, meta.len());
-                    let ll = LogData::from_content(contents);
-                    self.list_items.change_log_data(ll);
-                }
-
-                self.file.size = meta.len();
+    fn handle_file_event(&mut self, event: FileEvent) {
+        self.dirty = true;
 
-                if self.follow_mode {
-                    self.list_items.goto_end();
-                    self.update_logtext();
-                }
-            } else {
-                // trace!("No file changed");
+        match event {
+            FileEvent::Appended { text, size } => {
+                trace!("File size increased {:?}", text);
+                self.list_items.append_text(&text);
+                self.file.size = size;
+            }
+            FileEvent::Rotated { text, size } => {
+                // Keep the logs from the old file, like `tail -F`
+                info!("Following new {}", self.file.name);
+                self.list_items.append_rotated(&text);
+                self.file.size = size;
+            }
+            FileEvent::Missing => {
+                trace!("File gone!");
+                self.file.missing = true;
+                return;
+            }
+            FileEvent::Found => {
+                self.file.missing = false;
+                return;
             }
-        } else {
-            trace!("File gone!");
         }
 
-        Ok(())
+        if self.follow_mode {
+            self.list_items.goto_end();
+            self.update_logtext();
+        }
     }
 
     fn update_logtext(&mut self) {
         if let Some(log_text) = self.list_items.selected_item() {
             let ss: String = log_text.text().to_owned();
             let lines: Vec<_> = ss.lines().map(String::from).collect();
-            self.log_textarea = Some(TextArea::new(lines));
+            self.log_fields = log_text.fields();
+
+            // Exceptions are in the context, fall back to the text for other formats
+            let trace = match self.log_fields.iter().find(|(key, _)| key == "exception") {
+                Some((_, exception)) => StackTrace::parse(exception),
+                None => StackTrace::parse(&ss),
+            };
+            self.trace_view = trace.map(TraceView::new);
+
+            let mut pager = Pager::new(lines);
+            pager.set_search(self.list_items.search().cloned());
+            self.log_pager = Some(pager);
         } else {
-            self.log_textarea = None;
+            self.log_fields.clear();
+            self.trace_view = None;
+            self.log_pager = None;
+        }
+    }
+
+    fn visible_trace_view(&mut self) -> Option<&mut TraceView> {
+        if self.show_trace {
+            self.trace_view.as_mut()
+        } else {
+            None
         }
     }
 
@@ -346,13 +867,17 @@ impl<'a> App<'a> {
         let size = f.area();
         self.size = size;
 
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-            .split(f.area());
+        let [main_area, status_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(size);
 
-        self.render_log_list(f, &chunks[0]);
-        self.render_full_log(f, &chunks[1]);
+        let (list_area, content_area) = self.view.areas(main_area);
+        if let Some(area) = list_area {
+            self.render_log_list(f, &area);
+        }
+        if let Some(area) = content_area {
+            self.render_full_log(f, &area);
+        }
+        self.render_status_bar(f, status_area);
 
         // Render popup
         if self.app_mode == AppMode::ShowingKeybindings {
@@ -364,41 +889,129 @@ impl<'a> App<'a> {
             f.render_widget(Clear, area); //this clears out the background
             f.render_widget(&self.textarea, area);
         }
+
+        let prompt = match self.app_mode {
+            AppMode::EditingTimeRange => Some(&self.time_textarea),
+            AppMode::EditingSearch => Some(&self.search_textarea),
+            AppMode::EditingContentSearch => Some(&self.content_search_textarea),
+            _ => None,
+        };
+        if let Some(prompt) = prompt {
+            let area = ui::centered_rect(60, 20, size);
+            let area = Rect {
+                height: area.height.min(3),
+                ..area
+            };
+            f.render_widget(Clear, area);
+            f.render_widget(prompt, area);
+        }
     }
 
     fn render_full_log(&mut self, f: &mut Frame, area: &Rect) {
+        let mut title = "Content".to_owned();
+        if let Some(pager) = &self.log_pager {
+            title.push_str(&format!(
+                " {}/{}",
+                pager.cursor().0 + 1,
+                pager.lines().len()
+            ));
+            if pager.is_wrapping() {
+                title.push_str(" wrap");
+            }
+
+            if let (Some(search), Some((current, total))) =
+                (pager.search(), pager.search_position())
+            {
+                let current = current.map_or("-".to_owned(), |c| c.to_string());
+                title.push_str(&format!(" /{}/ {}/{}", search.text(), current, total));
+            }
+        }
+
         let block = Block::default()
-            .title(make_title(
-                "Content",
-                self.app_mode == AppMode::FocusLogText,
-            ))
+            .title(make_title(&title, self.app_mode == AppMode::FocusLogText))
             .borders(Borders::ALL)
             .style(Style::default().bg(Color::Blue));
 
-        // if let Some(log_text) = self.list_items.selected_item() {
-        if let Some(log_textarea) = &mut self.log_textarea {
-            log_textarea.set_block(block);
-            f.render_widget(&*log_textarea, *area);
+        let mut area = *area;
+        if !self.log_fields.is_empty() && self.log_pager.is_some() {
+            let fields_height = (self.log_fields.len() as u16 + 2).min(area.height / 3);
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(fields_height)])
+                .split(area);
+
+            area = chunks[0];
+            self.render_log_fields(f, &chunks[1]);
+        }
+
+        if let Some(trace_view) = self.visible_trace_view() {
+            trace_view.render(f, area, block.title("Stack trace"));
+            return;
+        }
+
+        let focused = self.app_mode == AppMode::FocusLogText;
+        if let Some(pager) = &mut self.log_pager {
+            pager.render(f, area, block, focused, &self.highlighter);
         }
-        // }
+    }
+
+    fn render_log_fields(&self, f: &mut Frame, area: &Rect) {
+        let key_width = self
+            .log_fields
+            .iter()
+            .map(|(key, _)| key.len())
+            .max()
+            .unwrap_or_default() as u16;
+
+        let rows = self.log_fields.iter().map(|(key, value)| {
+            // Only the first line, exceptions are shown in full in the content
+            let value = value.lines().next().unwrap_or_default();
+            Row::new(vec![key.clone(), value.to_owned()])
+        });
+
+        let widths = [Constraint::Length(key_width), Constraint::Fill(1)];
+        let table = Table::new(rows, widths)
+            .block(Block::default().title("Fields").borders(Borders::ALL))
+            .style(Style::default().bg(Color::Blue));
+
+        f.render_widget(table, *area);
     }
 
     fn render_log_list(&mut self, f: &mut Frame, area: &Rect) {
-        // Iterate through all elements in the `items` app and append some debug text to it.
-        // TODO: Cache or something se we don't recreate this every render
-        let mut item_state = self.list_items.state.clone();
+        // Only build items for the logs that fit, each log takes two lines
+        let rows = area.height.saturating_sub(2) as usize / 2;
+        let window = self.list_items.visible_window(rows);
+        let selected = self.list_items.state.selected();
+        let mut item_state = ListState::default()
+            .with_selected(selected.and_then(|ix| ix.checked_sub(window.start)));
+
+        let search = self.list_items.search();
         let items: Vec<ListItem> = self
             .list_items
-            .iter()
+            .iter_window(window.clone())
             .map(|i| {
-                let mut lines = vec![Line::from(i.info())];
+                let info_style = self.level_styles.style(i.level());
+                let marker = match i.level() {
+                    Some(_) => Span::styled("▌", info_style),
+                    None => Span::raw(" "),
+                };
+
+                let mut info = vec![marker.clone()];
+                info.extend(
+                    self.highlight(&i.info(), info_style, None)
+                        .into_iter()
+                        .map(|span| Span::styled(span.content.into_owned(), span.style)),
+                );
+                let mut lines = vec![Line::from(info)];
                 // for _ in 0..i.1 {
-                lines.push(Line::from(Span::styled(
-                    i.slug(30),
-                    Style::default().add_modifier(Modifier::ITALIC),
-                )));
+                let slug_style = info_style
+                    .remove_modifier(Modifier::BOLD)
+                    .add_modifier(Modifier::ITALIC);
+                let mut slug = vec![marker];
+                slug.extend(self.highlight(i.slug(30), slug_style, search));
+                lines.push(Line::from(slug));
                 // }
-                ListItem::new(lines).style(Style::default().fg(Color::Black).bg(Color::White))
+                ListItem::new(lines)
             })
             .collect();
 
@@ -408,16 +1021,36 @@ impl<'a> App<'a> {
             "   "
         };
 
+        let levels = self.list_items.levels();
+        let mut list_title = if levels.is_all() {
+            "List".to_owned()
+        } else {
+            format!("List [{}]", levels.describe())
+        };
+        if let Some(filter) = &self.filter {
+            list_title.push_str(&format!(" /{}/", filter.join("|")));
+        }
+        let time_range = self.list_items.time_range();
+        if !time_range.is_empty() {
+            list_title.push_str(&format!(" ({})", time_range.describe()));
+        }
+        if let Some(search) = search {
+            let (current, total) = self.list_items.search_position();
+            let current = current.map_or("-".to_owned(), |c| c.to_string());
+            list_title.push_str(&format!(" ?{}? {}/{}", search.text(), current, total));
+        }
+
         // Create a List from all list items and highlight the currently selected one
         let list_widget = List::new(items)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(make_title("List", self.app_mode == AppMode::Normal)),
+                    .title(make_title(&list_title, self.app_mode == AppMode::Normal)),
             )
+            // Reversed keeps the level colors readable on the selected row
             .highlight_style(
                 Style::default()
-                    .bg(Color::LightGreen)
+                    .add_modifier(Modifier::REVERSED)
                     .add_modifier(Modifier::BOLD),
             )
             .highlight_symbol(highlight_symbol);
@@ -426,7 +1059,91 @@ impl<'a> App<'a> {
 
         // We can now render the item list
         f.render_stateful_widget(list_widget, *area, &mut item_state);
-        self.list_items.state = item_state;
+
+        ui::render_scrollbar(
+            f,
+            *area,
+            self.list_items.len(),
+            selected.unwrap_or(window.start),
+            &[
+                (self.list_items.search_hits(), MATCH_COLOR),
+                (self.list_items.error_positions(), Color::Red),
+            ],
+        );
+    }
+
+    fn render_status_bar(&self, f: &mut Frame, area: Rect) {
+        let list = &self.list_items;
+        let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+        let on_off = |on: bool| {
+            if on {
+                Style::default().fg(Color::Black).bg(Color::Green)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            }
+        };
+
+        let mut spans = vec![Span::raw(format!(
+            " {} ({})",
+            self.file.name,
+            ui::format_size(self.file.size)
+        ))];
+        if self.file.missing {
+            spans.push(Span::styled(
+                " file gone, waiting",
+                Style::default().fg(Color::White).bg(Color::Red),
+            ));
+        }
+
+        let selected = list
+            .state
+            .selected()
+            .map_or("-".to_owned(), |ix| (ix + 1).to_string());
+        spans.push(separator.clone());
+        spans.push(Span::raw(format!(
+            "{}/{} of {}",
+            selected,
+            list.len(),
+            list.total_len()
+        )));
+
+        spans.push(separator.clone());
+        spans.push(Span::styled(" follow ", on_off(self.follow_mode)));
+
+        spans.push(separator.clone());
+        spans.push(Span::styled(" filter ", on_off(list.is_filtered())));
+        spans.push(Span::raw(format!(" levels: {}", list.levels().describe())));
+
+        if list.cutoff() > 0 {
+            spans.push(separator.clone());
+            spans.push(Span::raw(format!("{} cleared", list.cutoff())));
+        }
+
+        if self.view.layout != PaneLayout::SideBySide {
+            spans.push(separator.clone());
+            spans.push(Span::raw(self.view.layout.name()));
+        }
+
+        if !self.highlighter.is_enabled() {
+            spans.push(separator);
+            spans.push(Span::raw("highlight off"));
+        }
+
+        f.render_widget(
+            Paragraph::new(Line::from(spans)).style(Style::default().bg(Color::Black)),
+            area,
+        );
+    }
+
+    /// Style `text` with the highlight rules and search on top of `style`
+    fn highlight<'t>(&self, text: &'t str, style: Style, search: Option<&Search>) -> Vec<Span<'t>> {
+        let mut styles = vec![style; text.chars().count()];
+        self.highlighter.apply(text, &mut styles);
+        if let Some(search) = search {
+            search.apply(text, &mut styles);
+        }
+
+        highlight::styled_spans(text, &styles)
     }
 
     fn hide_popups(&mut self) {
@@ -456,3 +1173,156 @@ impl<'a> App<'a> {
         f.render_stateful_widget(table, area, &mut self.keybindings_state)
     }
 }
+
+fn prompt_block<'b>(title: &'b str, error: Option<String>) -> Block<'b> {
+    let block = Block::default().borders(Borders::ALL);
+    match error {
+        Some(error) => block
+            .title(format!("{} - {}", title, error))
+            .border_style(Style::default().fg(Color::Red)),
+        None => block.title(title),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyEvent;
+
+    use super::*;
+
+    const TRACE_LOG: &str = "[2023-02-14 13:42:48] local.INFO: started [] []
+[2023-02-14 13:42:49] local.ERROR: Division by zero [object] (DivisionByZeroError(code: 0): Division by zero at /var/www/html/app/Http/Controllers/Foo.php:12)
+[stacktrace]
+#0 /var/www/html/app/Http/Controllers/Foo.php(12): App\\Http\\Controllers\\Foo->divide()
+#1 /var/www/html/app/Http/Middleware/Auth.php(20): App\\Http\\Controllers\\Foo->index()
+#2 {main}
+";
+
+    fn make_app(content: &str) -> App<'static> {
+        let parser = RawParser::default();
+        let data = LogData::from_content(&parser, content.to_owned());
+        let file = FileInfo {
+            name: "test.log".to_owned(),
+            size: content.len() as u64,
+            missing: false,
+        };
+
+        App::new(file, parser, data, Config::default(), None)
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        app.handle_event(AppEvent::Input(event)).unwrap();
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn content_search_in_entry_with_trace() {
+        let mut app = make_app(TRACE_LOG);
+        press(&mut app, KeyCode::End);
+        press(&mut app, KeyCode::Tab);
+        assert!(app.visible_trace_view().is_some());
+
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!(app.app_mode, AppMode::EditingContentSearch);
+        type_text(&mut app, "index");
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(app.app_mode, AppMode::FocusLogText);
+        assert!(app.visible_trace_view().is_none());
+        let pager = app.log_pager.as_ref().unwrap();
+        assert_eq!(pager.search_position(), Some((Some(1), 1)));
+        assert_eq!(pager.cursor().0, 3);
+
+        // n and N also leave the trace view
+        press(&mut app, KeyCode::Char('t'));
+        assert!(app.visible_trace_view().is_some());
+        press(&mut app, KeyCode::Char('n'));
+        assert!(app.visible_trace_view().is_none());
+    }
+
+    #[test]
+    fn only_changes_mark_dirty() {
+        let mut app = make_app(TRACE_LOG);
+        app.dirty = false;
+
+        app.handle_event(AppEvent::Input(Event::FocusGained))
+            .unwrap();
+        assert!(!app.dirty);
+
+        press(&mut app, KeyCode::Down);
+        assert!(app.dirty);
+        app.dirty = false;
+
+        let text = "[2023-02-14 13:42:50] local.INFO: appended [] []\n".to_owned();
+        let size = app.file.size + text.len() as u64;
+        app.handle_event(AppEvent::File(FileEvent::Appended { text, size }))
+            .unwrap();
+        assert!(app.dirty);
+        assert_eq!(app.file.size, size);
+        app.dirty = false;
+
+        app.handle_event(AppEvent::Input(Event::Resize(80, 24)))
+            .unwrap();
+        assert!(app.dirty);
+        app.dirty = false;
+
+        app.handle_event(AppEvent::Input(Event::FocusLost)).unwrap();
+        assert!(!app.dirty);
+    }
+
+    #[test]
+    fn rotated_file_starts_new_log() {
+        let mut app = make_app("[2023-02-14 13:42:48] local.INFO: old");
+
+        let text = "[2023-02-14 13:42:49] local.INFO: new\n".to_owned();
+        let size = text.len() as u64;
+        app.handle_event(AppEvent::File(FileEvent::Rotated { text, size }))
+            .unwrap();
+
+        let texts: Vec<_> = app.list_items.iter().map(|l| l.text()).collect();
+        assert_eq!(texts, vec!["old", "new\n"]);
+        assert_eq!(app.file.size, size);
+    }
+
+    #[test]
+    fn tab_skips_hidden_content() {
+        let mut app = make_app(TRACE_LOG);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Char('l'));
+        assert_eq!(app.view.layout, PaneLayout::ListOnly);
+
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.app_mode, AppMode::Normal);
+
+        press(&mut app, KeyCode::Char('l'));
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.app_mode, AppMode::FocusLogText);
+    }
+
+    #[test]
+    fn list_keys_keep_pager_state() {
+        let mut app = make_app(TRACE_LOG);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Char('w'));
+        press(&mut app, KeyCode::Esc);
+
+        for code in ['h', 'l', '+', 't'] {
+            press(&mut app, KeyCode::Char(code));
+        }
+        assert!(app.log_pager.as_ref().unwrap().is_wrapping());
+
+        press(&mut app, KeyCode::Down);
+        assert!(!app.log_pager.as_ref().unwrap().is_wrapping());
+
+        press(&mut app, KeyCode::Char('c'));
+        assert!(app.log_pager.is_none());
+    }
+}
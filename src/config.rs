@@ -0,0 +1,257 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use ratatui::style::{Color, Modifier, Style};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::level::Level;
+
+/// User settings read from `~/.config/termilog/config.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Rewrite paths from the logs before opening them, e.g. from a container to a local checkout
+    pub path_map: Vec<PathMapping>,
+
+    /// Style parts of the logs matching a pattern, later rules are applied on top of earlier ones
+    pub highlight: Vec<HighlightRule>,
+
+    /// Style of the list entries per level
+    pub levels: LevelStyleConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PathMapping {
+    pub from: String,
+    pub to: String,
+}
+
+/// Text style, colors are names like `red`, `#ff8800` or a 256 color index
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct StyleConfig {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl From<StyleConfig> for Style {
+    fn from(config: StyleConfig) -> Self {
+        let mut style = Style::default();
+        if let Some(fg) = config.fg {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = config.bg {
+            style = style.bg(bg);
+        }
+
+        for (enabled, modifier) in [
+            (config.bold, Modifier::BOLD),
+            (config.dim, Modifier::DIM),
+            (config.italic, Modifier::ITALIC),
+            (config.underline, Modifier::UNDERLINED),
+        ] {
+            if enabled {
+                style = style.add_modifier(modifier);
+            }
+        }
+
+        style
+    }
+}
+
+/// Styles per level, levels that are not set keep the default style
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LevelStyleConfig {
+    pub debug: Option<StyleConfig>,
+    pub info: Option<StyleConfig>,
+    pub notice: Option<StyleConfig>,
+    pub warning: Option<StyleConfig>,
+    pub error: Option<StyleConfig>,
+    pub critical: Option<StyleConfig>,
+    pub alert: Option<StyleConfig>,
+    pub emergency: Option<StyleConfig>,
+}
+
+impl LevelStyleConfig {
+    pub fn get(&self, level: Level) -> Option<StyleConfig> {
+        match level {
+            Level::Debug => self.debug,
+            Level::Info => self.info,
+            Level::Notice => self.notice,
+            Level::Warning => self.warning,
+            Level::Error => self.error,
+            Level::Critical => self.critical,
+            Level::Alert => self.alert,
+            Level::Emergency => self.emergency,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HighlightRule {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    #[serde(flatten)]
+    pub style: StyleConfig,
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+impl PathMapping {
+    /// Parse `from=to` as given on the command line
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        match arg.split_once('=') {
+            Some((from, to)) if !from.is_empty() => Ok(PathMapping {
+                from: from.to_owned(),
+                to: to.to_owned(),
+            }),
+            _ => Err(format!("expected FROM=TO, got '{}'", arg)),
+        }
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(config_dir.join("termilog").join("config.toml"))
+    }
+
+    /// Load config from `path`, a missing file gives the default config
+    pub fn load(path: &Path) -> io::Result<Config> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(err),
+        };
+
+        toml::from_str(&content).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })
+    }
+
+    /// Map a path from the log to a local path using the longest prefix of whole components
+    pub fn map_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        let mapping = self
+            .path_map
+            .iter()
+            .filter_map(|m| Some((m, path.strip_prefix(&m.from).ok()?)))
+            .max_by_key(|(m, _)| m.from.len());
+
+        match mapping {
+            Some((m, rest)) if rest.as_os_str().is_empty() => PathBuf::from(&m.to),
+            Some((m, rest)) => Path::new(&m.to).join(rest),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_longest_prefix() {
+        let config: Config = toml::from_str(
+            r#"
+[[path_map]]
+from = "/var/www"
+to = "/srv"
+
+[[path_map]]
+from = "/var/www/html"
+to = "/home/me/app"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.map_path("/var/www/html/app/Foo.php"),
+            PathBuf::from("/home/me/app/app/Foo.php")
+        );
+        assert_eq!(
+            config.map_path("/var/www/other/Foo.php"),
+            PathBuf::from("/srv/other/Foo.php")
+        );
+        assert_eq!(config.map_path("app/Foo.php"), PathBuf::from("app/Foo.php"));
+    }
+
+    #[test]
+    fn map_whole_components() {
+        let config = Config {
+            path_map: vec![PathMapping::parse("/var/www/html=/home/me/app").unwrap()],
+            ..Config::default()
+        };
+
+        assert_eq!(
+            config.map_path("/var/www/html2/x.php"),
+            PathBuf::from("/var/www/html2/x.php")
+        );
+        assert_eq!(
+            config.map_path("/var/www/html/x.php"),
+            PathBuf::from("/home/me/app/x.php")
+        );
+        assert_eq!(
+            config.map_path("/var/www/html"),
+            PathBuf::from("/home/me/app")
+        );
+    }
+
+    #[test]
+    fn highlight_rules() {
+        let config: Config = toml::from_str(
+            r##"
+[[highlight]]
+pattern = "SQLSTATE\\[\\w+\\]"
+fg = "red"
+bold = true
+
+[[highlight]]
+pattern = "tenant-\\d+"
+fg = "#00ffff"
+"##,
+        )
+        .unwrap();
+
+        assert_eq!(config.highlight.len(), 2);
+        assert!(config.highlight[0].pattern.is_match("SQLSTATE[23000]"));
+        assert_eq!(
+            Style::from(config.highlight[0].style),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(config.highlight[1].style.fg, Some(Color::Rgb(0, 255, 255)));
+
+        let invalid = toml::from_str::<Config>("[[highlight]]\npattern = \"(\"");
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn parse_path_mapping_arg() {
+        assert_eq!(
+            PathMapping::parse("/var/www/html=."),
+            Ok(PathMapping {
+                from: "/var/www/html".to_owned(),
+                to: ".".to_owned()
+            })
+        );
+        assert!(PathMapping::parse("/var/www/html").is_err());
+    }
+}
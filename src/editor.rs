@@ -0,0 +1,158 @@
+use std::{env, io, path::Path, process::Command};
+
+/// A `/path/File.php(123)` or `File.php:123` reference found in a log
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FileRef {
+    pub path: String,
+    pub line: u32,
+}
+
+fn is_path_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'/' | b'\\' | b'.' | b'_' | b'-' | b'~' | b'@' | b'+')
+}
+
+/// Parse the `(123)` or `:123` following a path, returns line and length of the suffix
+fn parse_line_suffix(rest: &[u8]) -> Option<(u32, usize)> {
+    let (open, close) = match rest.first()? {
+        b'(' => (1, Some(b')')),
+        b':' => (1, None),
+        _ => return None,
+    };
+
+    let digits = rest[open..]
+        .iter()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    let line = std::str::from_utf8(&rest[open..open + digits])
+        .ok()?
+        .parse()
+        .ok()?;
+
+    let mut len = open + digits;
+    if let Some(close) = close {
+        if rest.get(len) != Some(&close) {
+            return None;
+        }
+        len += 1;
+    }
+
+    Some((line, len))
+}
+
+/// All file references in `line` with their byte range
+pub fn find_file_refs(line: &str) -> Vec<(usize, usize, FileRef)> {
+    let bytes = line.as_bytes();
+    let mut refs = Vec::new();
+
+    let mut ix = 0;
+    while ix < bytes.len() {
+        if !is_path_char(bytes[ix]) {
+            ix += 1;
+            continue;
+        }
+
+        let start = ix;
+        while ix < bytes.len() && is_path_char(bytes[ix]) {
+            ix += 1;
+        }
+
+        // Needs a file extension to not match things like `code: 0`
+        let path = &line[start..ix];
+        let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        if !file_name.contains('.') {
+            continue;
+        }
+
+        if let Some((line_nr, len)) = parse_line_suffix(&bytes[ix..]) {
+            refs.push((
+                start,
+                ix + len,
+                FileRef {
+                    path: path.to_owned(),
+                    line: line_nr,
+                },
+            ));
+            ix += len;
+        }
+    }
+
+    refs
+}
+
+/// The file reference under `col` (in chars), or the first one on the line
+pub fn file_ref_at(line: &str, col: usize) -> Option<FileRef> {
+    let byte_col = line
+        .char_indices()
+        .nth(col)
+        .map_or(line.len(), |(ix, _)| ix);
+    let refs = find_file_refs(line);
+
+    refs.iter()
+        .find(|(start, end, _)| (*start..*end).contains(&byte_col))
+        .or(refs.first())
+        .map(|(_, _, file_ref)| file_ref.clone())
+}
+
+/// Run `$EDITOR +line path` and wait for it to exit. The terminal must be restored by the caller.
+pub fn open(path: &Path, line: u32) -> io::Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_owned());
+
+    // Allow things like `EDITOR="code -w"`
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or("vi");
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(format!("+{}", line))
+        .arg(path)
+        .status()?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_ref(path: &str, line: u32) -> FileRef {
+        FileRef {
+            path: path.to_owned(),
+            line,
+        }
+    }
+
+    #[test]
+    fn find_stack_frame_and_location() {
+        let line = "#0 /var/www/html/app/Foo.php(54): App\\Foo->index() at Bar.php:12)";
+        let refs = find_file_refs(line);
+
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs[0], (3, 32, file_ref("/var/www/html/app/Foo.php", 54)));
+        assert_eq!(refs[1].2, file_ref("Bar.php", 12));
+    }
+
+    #[test]
+    fn ignore_references_without_file() {
+        assert_eq!(find_file_refs("Exception(code: 0): at 12:30"), vec![]);
+        assert_eq!(find_file_refs("callAction('index', Array)"), vec![]);
+        assert_eq!(find_file_refs("/a/Foo.php(12"), vec![]);
+    }
+
+    #[test]
+    fn ref_under_cursor() {
+        let line = "/a/First.php:1 and /b/Second.php:2";
+
+        assert_eq!(file_ref_at(line, 25), Some(file_ref("/b/Second.php", 2)));
+        assert_eq!(file_ref_at(line, 16), Some(file_ref("/a/First.php", 1)));
+        assert_eq!(file_ref_at("nothing here", 3), None);
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    cell::Cell,
+    io,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use crossterm::event::{self, Event};
+use log::error;
+
+use crate::watcher::FileEvent;
+
+/// Everything the main loop reacts to
+#[derive(Debug)]
+pub enum AppEvent {
+    Input(Event),
+    File(FileEvent),
+}
+
+/// Channel fed by the input thread, file watchers send through `sender()`.
+/// The input thread reads one event at a time and waits for `resume_input` before
+/// reading the next, so it leaves the terminal alone while e.g. an editor runs.
+pub struct Events {
+    tx: Sender<AppEvent>,
+    rx: Receiver<AppEvent>,
+    resume_tx: Sender<()>,
+    /// An input event was handed out and the input thread waits to read the next
+    input_waiting: Cell<bool>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        let (resume_tx, resume_rx) = mpsc::channel();
+        let events = Events {
+            tx,
+            rx,
+            resume_tx,
+            input_waiting: Cell::new(false),
+        };
+
+        events.spawn_input(resume_rx);
+
+        events
+    }
+
+    pub fn sender(&self) -> Sender<AppEvent> {
+        self.tx.clone()
+    }
+
+    /// Wait for the next event
+    pub fn next(&self) -> io::Result<AppEvent> {
+        let event = self.rx.recv().map_err(io::Error::other)?;
+        self.taken(&event);
+        Ok(event)
+    }
+
+    /// Next event if one is already waiting
+    pub fn try_next(&self) -> Option<AppEvent> {
+        let event = self.rx.try_recv().ok()?;
+        self.taken(&event);
+        Some(event)
+    }
+
+    /// Let the input thread read the next event once the last one is handled
+    pub fn resume_input(&self) {
+        if self.input_waiting.replace(false) {
+            let _ = self.resume_tx.send(());
+        }
+    }
+
+    fn taken(&self, event: &AppEvent) {
+        if matches!(event, AppEvent::Input(_)) {
+            self.input_waiting.set(true);
+        }
+    }
+
+    fn spawn_input(&self, resume_rx: Receiver<()>) {
+        let tx = self.tx.clone();
+
+        thread::spawn(move || loop {
+            match event::read() {
+                Ok(event) => {
+                    if tx.send(AppEvent::Input(event)).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    error!("Failed to read input: {}", err);
+                    return;
+                }
+            }
+
+            // The event might start an editor that needs the terminal
+            if resume_rx.recv().is_err() {
+                return;
+            }
+        });
+    }
+}
@@ -0,0 +1,124 @@
+use ratatui::style::Style;
+use ratatui::text::Span;
+
+use crate::config::HighlightRule;
+
+/// User defined highlight rules from the config
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    rules: Vec<(regex::Regex, Style)>,
+    enabled: bool,
+}
+
+impl Highlighter {
+    pub fn new(rules: &[HighlightRule]) -> Self {
+        Highlighter {
+            rules: rules
+                .iter()
+                .map(|rule| (rule.pattern.clone(), rule.style.into()))
+                .collect(),
+            enabled: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Patch the style of the chars in `text` matching a rule
+    pub fn apply(&self, text: &str, styles: &mut [Style]) {
+        if !self.enabled {
+            return;
+        }
+
+        for (regex, style) in &self.rules {
+            for m in regex.find_iter(text) {
+                patch_chars(text, m.range(), *style, styles);
+            }
+        }
+    }
+}
+
+/// Patch the style of the chars in the byte range `bytes` of `text`
+pub fn patch_chars(text: &str, bytes: std::ops::Range<usize>, style: Style, styles: &mut [Style]) {
+    let start = text[..bytes.start].chars().count();
+    let len = text[bytes].chars().count();
+
+    for s in styles.iter_mut().skip(start).take(len) {
+        *s = s.patch(style);
+    }
+}
+
+/// Split `text` into spans of chars with the same style, `styles` has one style per char
+pub fn styled_spans<'t>(text: &'t str, styles: &[Style]) -> Vec<Span<'t>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut current = styles.first().copied().unwrap_or_default();
+
+    for ((ix, _), style) in text.char_indices().zip(styles) {
+        if *style != current {
+            spans.push(Span::styled(&text[start..ix], current));
+            start = ix;
+            current = *style;
+        }
+    }
+    spans.push(Span::styled(&text[start..], current));
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Color, Modifier};
+
+    use super::*;
+    use crate::config::Config;
+
+    fn highlighter() -> Highlighter {
+        let config: Config = toml::from_str(
+            r#"
+[[highlight]]
+pattern = "[0-9a-f]{8}"
+dim = true
+
+[[highlight]]
+pattern = "SQLSTATE"
+fg = "red"
+"#,
+        )
+        .unwrap();
+
+        Highlighter::new(&config.highlight)
+    }
+
+    #[test]
+    fn rules_compose_with_base_style() {
+        let text = "ä SQLSTATE deadbeef";
+        let base = Style::default().fg(Color::Blue);
+        let mut styles = vec![base; text.chars().count()];
+        highlighter().apply(text, &mut styles);
+
+        let spans = styled_spans(text, &styles);
+        let texts: Vec<_> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(texts, vec!["ä ", "SQLSTATE", " ", "deadbeef"]);
+        assert_eq!(spans[1].style, Style::default().fg(Color::Red));
+        assert_eq!(spans[3].style, base.add_modifier(Modifier::DIM));
+    }
+
+    #[test]
+    fn toggle_rules() {
+        let mut highlighter = highlighter();
+        highlighter.toggle();
+
+        let mut styles = vec![Style::default(); 8];
+        highlighter.apply("SQLSTATE", &mut styles);
+        assert_eq!(
+            styled_spans("SQLSTATE", &styles),
+            vec![Span::raw("SQLSTATE")]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::config::LevelStyleConfig;
+
+/// PSR-3 log levels, ordered by severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl Level {
+    pub const ALL: [Level; 8] = [
+        Level::Debug,
+        Level::Info,
+        Level::Notice,
+        Level::Warning,
+        Level::Error,
+        Level::Critical,
+        Level::Alert,
+        Level::Emergency,
+    ];
+
+    /// Parse a level name, case insensitive and with common aliases like `warn`
+    pub fn parse(name: &str) -> Option<Level> {
+        const NAMES: [(&str, Level); 15] = [
+            ("debug", Level::Debug),
+            ("trace", Level::Debug),
+            ("info", Level::Info),
+            ("information", Level::Info),
+            ("notice", Level::Notice),
+            ("warning", Level::Warning),
+            ("warn", Level::Warning),
+            ("error", Level::Error),
+            ("err", Level::Error),
+            ("critical", Level::Critical),
+            ("crit", Level::Critical),
+            ("fatal", Level::Critical),
+            ("alert", Level::Alert),
+            ("emergency", Level::Emergency),
+            ("emerg", Level::Emergency),
+        ];
+
+        NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, level)| *level)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Notice => "NOTICE",
+            Level::Warning => "WARNING",
+            Level::Error => "ERROR",
+            Level::Critical => "CRITICAL",
+            Level::Alert => "ALERT",
+            Level::Emergency => "EMERGENCY",
+        }
+    }
+
+    /// Position in `Level::ALL`
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// Style of the level in the list unless configured otherwise
+    pub fn default_style(&self) -> Style {
+        let style = Style::default();
+        match self {
+            Level::Debug => style.fg(Color::DarkGray),
+            Level::Info => style,
+            Level::Notice => style.fg(Color::Cyan),
+            Level::Warning => style.fg(Color::Yellow),
+            Level::Error => style.fg(Color::Red),
+            Level::Critical => style.fg(Color::Red).add_modifier(Modifier::BOLD),
+            Level::Alert => style.fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            Level::Emergency => style
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        }
+    }
+}
+
+/// Style of each level in the list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelStyles {
+    styles: [Style; 8],
+}
+
+impl Default for LevelStyles {
+    fn default() -> Self {
+        LevelStyles {
+            styles: Level::ALL.map(|level| level.default_style()),
+        }
+    }
+}
+
+impl LevelStyles {
+    /// Default styles replaced by the ones set in the config
+    pub fn new(config: &LevelStyleConfig) -> Self {
+        LevelStyles {
+            styles: Level::ALL.map(|level| {
+                config
+                    .get(level)
+                    .map_or_else(|| level.default_style(), Style::from)
+            }),
+        }
+    }
+
+    /// Style of a log, logs without a known level use the terminal colors
+    pub fn style(&self, level: Option<Level>) -> Style {
+        level.map_or(Style::default(), |l| self.styles[l.index()])
+    }
+}
+
+/// Which levels are shown in the list. Logs without a known level are always shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelFilter {
+    enabled: [bool; 8],
+}
+
+impl Default for LevelFilter {
+    fn default() -> Self {
+        LevelFilter { enabled: [true; 8] }
+    }
+}
+
+impl LevelFilter {
+    pub fn is_shown(&self, level: Option<Level>) -> bool {
+        level.is_none_or(|l| self.enabled[l.index()])
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.enabled.iter().all(|e| *e)
+    }
+
+    pub fn toggle(&mut self, level: Level) {
+        self.enabled[level.index()] = !self.enabled[level.index()];
+    }
+
+    /// Show only `level` and above
+    pub fn set_min(&mut self, level: Level) {
+        for l in Level::ALL {
+            self.enabled[l.index()] = l >= level;
+        }
+    }
+
+    /// Lowest shown level if all levels above it are shown too
+    pub fn min_level(&self) -> Option<Level> {
+        let first = self.enabled.iter().position(|e| *e)?;
+        if self.enabled[first..].iter().all(|e| *e) {
+            Some(Level::ALL[first])
+        } else {
+            None
+        }
+    }
+
+    /// Step the minimum level up one severity, wrapping around to all levels
+    pub fn cycle_min(&mut self) {
+        let next = match self.min_level() {
+            Some(Level::Emergency) => Level::Debug,
+            Some(level) => Level::ALL[level.index() + 1],
+            None => Level::Debug,
+        };
+        self.set_min(next);
+    }
+
+    /// Short description of the shown levels, like `ERROR+` or `INFO,ERROR`
+    pub fn describe(&self) -> String {
+        match self.min_level() {
+            Some(Level::Debug) => "all".to_owned(),
+            Some(level) => format!("{}+", level.name()),
+            None => {
+                let names: Vec<_> = Level::ALL
+                    .iter()
+                    .filter(|l| self.enabled[l.index()])
+                    .map(|l| l.name())
+                    .collect();
+
+                if names.is_empty() {
+                    "none".to_owned()
+                } else {
+                    names.join(",")
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_names_and_aliases() {
+        assert_eq!(Level::parse("ERROR"), Some(Level::Error));
+        assert_eq!(Level::parse("warn"), Some(Level::Warning));
+        assert_eq!(Level::parse("Warning"), Some(Level::Warning));
+        assert_eq!(Level::parse("emerg"), Some(Level::Emergency));
+        assert_eq!(Level::parse("apple"), None);
+    }
+
+    #[test]
+    fn ordered_by_severity() {
+        assert!(Level::Debug < Level::Info);
+        assert!(Level::Warning < Level::Error);
+        assert!(Level::Alert < Level::Emergency);
+        assert_eq!(Level::ALL[Level::Error.index()], Level::Error);
+    }
+
+    #[test]
+    fn configured_level_styles() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+[levels]
+debug = { fg = "blue" }
+error = { fg = "magenta", underline = true }
+"#,
+        )
+        .unwrap();
+        let styles = LevelStyles::new(&config.levels);
+
+        assert_eq!(
+            styles.style(Some(Level::Debug)),
+            Style::default().fg(Color::Blue)
+        );
+        assert_eq!(
+            styles.style(Some(Level::Error)),
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::UNDERLINED)
+        );
+        assert_eq!(
+            styles.style(Some(Level::Warning)),
+            Level::Warning.default_style()
+        );
+        assert_eq!(styles.style(None), Style::default());
+
+        let typo = toml::from_str::<crate::config::Config>("[levels]\nerorr = { fg = \"red\" }");
+        assert!(typo.is_err());
+    }
+
+    #[test]
+    fn filter_min_level() {
+        let mut filter = LevelFilter::default();
+        assert!(filter.is_all());
+        assert_eq!(filter.describe(), "all");
+
+        filter.set_min(Level::Error);
+        assert!(!filter.is_shown(Some(Level::Warning)));
+        assert!(filter.is_shown(Some(Level::Critical)));
+        assert!(filter.is_shown(None));
+        assert_eq!(filter.describe(), "ERROR+");
+
+        filter.cycle_min();
+        assert_eq!(filter.min_level(), Some(Level::Critical));
+
+        filter.set_min(Level::Emergency);
+        filter.cycle_min();
+        assert!(filter.is_all());
+    }
+
+    #[test]
+    fn filter_toggle_levels() {
+        let mut filter = LevelFilter::default();
+        filter.set_min(Level::Error);
+        filter.toggle(Level::Info);
+
+        assert_eq!(filter.min_level(), None);
+        assert_eq!(filter.describe(), "INFO,ERROR,CRITICAL,ALERT,EMERGENCY");
+
+        // Not a minimum level so cycling starts over from the bottom
+        filter.cycle_min();
+        assert!(filter.is_all());
+
+        for level in Level::ALL {
+            filter.toggle(level);
+        }
+        assert_eq!(filter.describe(), "none");
+    }
+}
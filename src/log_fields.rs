@@ -0,0 +1,178 @@
+use serde_json::{Map, Value};
+
+/// Key/value pair from the context or extra of a log entry
+pub type Field = (String, String);
+
+/// Find the trailing `{context} {extra}` JSON Laravel writes after the message.
+/// Returns the byte ranges of context and extra in `text`.
+pub fn split_trailing_json(text: &str) -> Option<((usize, usize), (usize, usize))> {
+    let extra_end = text.trim_end().len();
+    let extra_start = json_start_backwards(text, extra_end)?;
+
+    let context_end = text[..extra_start].trim_end().len();
+    if context_end == extra_start {
+        return None;
+    }
+    let context_start = json_start_backwards(text, context_end)?;
+
+    if context_start > 0 && !text[..context_start].ends_with(' ') {
+        return None;
+    }
+
+    Some(((context_start, context_end), (extra_start, extra_end)))
+}
+
+/// Walk backwards from `end` to the bracket opening the JSON value ending there
+fn json_start_backwards(text: &str, end: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    if !matches!(bytes.get(end.checked_sub(1)?), Some(b'}' | b']')) {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut ix = end;
+    while ix > 0 {
+        ix -= 1;
+        match bytes[ix] {
+            b'}' | b']' => depth += 1,
+            b'{' | b'[' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(ix);
+                }
+            }
+            b'"' => loop {
+                // Skip the string, quotes inside it are always escaped
+                ix = ix.checked_sub(1)?;
+                if bytes[ix] == b'"' && !is_escaped(bytes, ix) {
+                    break;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn is_escaped(bytes: &[u8], ix: usize) -> bool {
+    let backslashes = bytes[..ix]
+        .iter()
+        .rev()
+        .take_while(|c| **c == b'\\')
+        .count();
+    backslashes % 2 == 1
+}
+
+/// Parse JSON written by Monolog. Line breaks inside strings are allowed since
+/// Laravel keeps them when writing stack traces.
+pub fn parse_lenient(json: &str) -> Option<Value> {
+    if let Ok(value) = serde_json::from_str(json) {
+        return Some(value);
+    }
+
+    let mut escaped = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut prev_backslash = false;
+    for c in json.chars() {
+        match c {
+            '"' if !prev_backslash => in_string = !in_string,
+            '\n' if in_string => {
+                escaped.push_str("\\n");
+                continue;
+            }
+            '\r' if in_string => {
+                escaped.push_str("\\r");
+                continue;
+            }
+            '\t' if in_string => {
+                escaped.push_str("\\t");
+                continue;
+            }
+            _ => {}
+        }
+
+        prev_backslash = c == '\\' && !prev_backslash;
+        escaped.push(c);
+    }
+
+    serde_json::from_str(&escaped).ok()
+}
+
+/// Flatten a context or extra object into displayable key/value pairs
+pub fn to_fields(value: Option<Value>, prefix: &str) -> Vec<Field> {
+    match value {
+        Some(Value::Object(map)) => map_to_fields(map, prefix),
+        _ => Vec::new(),
+    }
+}
+
+fn map_to_fields(map: Map<String, Value>, prefix: &str) -> Vec<Field> {
+    map.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s,
+                v => v.to_string(),
+            };
+            (format!("{prefix}{key}"), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_context_and_extra() {
+        let text = "Incoming webhook {\"userId\":42,\"name\":\"a } b\"} []\n";
+        let (context, extra) = split_trailing_json(text).unwrap();
+
+        assert_eq!(
+            &text[context.0..context.1],
+            "{\"userId\":42,\"name\":\"a } b\"}"
+        );
+        assert_eq!(&text[extra.0..extra.1], "[]");
+    }
+
+    #[test]
+    fn split_needs_both_context_and_extra() {
+        assert_eq!(
+            split_trailing_json("Incoming webhook {\"userId\":42}"),
+            None
+        );
+        assert_eq!(split_trailing_json("Incoming webhook: 7 "), None);
+        assert_eq!(split_trailing_json("Incoming webhook{} []"), None);
+    }
+
+    #[test]
+    fn split_escaped_quotes() {
+        let text = r#"Failed {"exception":"[object] (Exception(code: 0): \"quoted\\\" at /a.php:1)"} {"ip":"::1"}"#;
+        let (context, extra) = split_trailing_json(text).unwrap();
+
+        assert!(text[context.0..context.1].starts_with("{\"exception\""));
+        assert_eq!(&text[extra.0..extra.1], "{\"ip\":\"::1\"}");
+    }
+
+    #[test]
+    fn parse_multiline_stack_trace() {
+        let json = "{\"exception\":\"[object] (Exception(code: 0): Oops at /a.php:1)\n[stacktrace]\n#0 {main}\n\"}";
+        let fields = to_fields(parse_lenient(json), "");
+
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].0, "exception");
+        assert!(fields[0].1.ends_with("#0 {main}\n"));
+    }
+
+    #[test]
+    fn fields_from_non_object() {
+        assert_eq!(to_fields(parse_lenient("[]"), "extra."), vec![]);
+        assert_eq!(
+            to_fields(parse_lenient("{\"a\":{\"b\":1},\"c\":\"d\"}"), "extra."),
+            vec![
+                ("extra.a".to_owned(), "{\"b\":1}".to_owned()),
+                ("extra.c".to_owned(), "d".to_owned())
+            ]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use serde_json::{Map, Value};
+
+use crate::log_fields;
+use crate::log_line::LogLine;
+
+/// Describes how entries are laid out in a log file. The parser uses it to
+/// find where each entry starts and `LogLine` gets its fields from it.
+pub trait LogFormat: Sync {
+    /// Name used to pick the format on the command line
+    fn name(&self) -> &'static str;
+
+    /// Returns true if a new log entry starts at the beginning of `text`.
+    /// `text` always starts at the beginning of a line.
+    fn is_entry_start(&self, text: &[u8]) -> bool;
+
+    /// Split a single log entry into its fields
+    fn parse_line<'a>(&self, source: &'a str) -> LogLine<'a>;
+}
+
+pub static LARAVEL: Laravel = Laravel {};
+pub static MONOLOG_JSON: MonologJson = MonologJson {};
+
+/// All built in formats
+pub static FORMATS: [&dyn LogFormat; 2] = [&LARAVEL, &MONOLOG_JSON];
+
+pub fn by_name(name: &str) -> Option<&'static dyn LogFormat> {
+    FORMATS.iter().find(|f| f.name() == name).copied()
+}
+
+/// Guess the format from the first entry in `content`, falls back to Laravel
+pub fn detect(content: &str) -> &'static dyn LogFormat {
+    let first_line = content.trim_start().as_bytes();
+
+    FORMATS
+        .iter()
+        .find(|f| f.is_entry_start(first_line))
+        .copied()
+        .unwrap_or(&LARAVEL)
+}
+
+/// Default Laravel format: `[2023-02-14 13:42:48] local.INFO: message`. Laravel 10+
+/// can also write ISO-8601 dates like `[2024-05-01T12:00:00.123456+00:00]`.
+pub struct Laravel {}
+
+/// Walks a date pattern where `d` matches any digit
+struct DateMatcher<'t> {
+    text: &'t [u8],
+    ix: usize,
+}
+
+impl DateMatcher<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.ix).copied()
+    }
+
+    /// Match `pattern` exactly, on failure `ix` points past the failing char
+    fn pattern(&mut self, pattern: &[u8]) -> bool {
+        for mc in pattern {
+            let Some(c) = self.peek() else {
+                return false;
+            };
+            self.ix += 1;
+
+            let res = match mc {
+                b'd' => c.is_ascii_digit(),
+                _ => *mc == c,
+            };
+
+            if !res {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn one_of(&mut self, chars: &[u8]) -> bool {
+        match self.peek() {
+            Some(c) if chars.contains(&c) => {
+                self.ix += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.ix;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.ix += 1;
+        }
+        self.ix - start
+    }
+
+    /// `[dddd-dd-dd dd:dd:dd] ` with optional `T` separator, fraction and offset
+    fn laravel_date(&mut self) -> bool {
+        if !self.pattern(b"[dddd-dd-dd") || !self.one_of(b" T") || !self.pattern(b"dd:dd:dd") {
+            return false;
+        }
+
+        if self.one_of(b".") && self.digits() == 0 {
+            return false;
+        }
+
+        if !self.one_of(b"Z") && self.one_of(b"+-") {
+            if !self.pattern(b"dd") {
+                return false;
+            }
+            self.one_of(b":");
+            if !self.pattern(b"dd") {
+                return false;
+            }
+        }
+
+        self.pattern(b"] ")
+    }
+}
+
+impl Laravel {
+    /// Returns if `text` starts with a date and the length of the date, or
+    /// where the match failed
+    pub fn match_date(&self, text: &[u8]) -> (bool, usize) {
+        let mut matcher = DateMatcher { text, ix: 0 };
+        let res = matcher.laravel_date();
+
+        (res, matcher.ix)
+    }
+}
+
+impl LogFormat for Laravel {
+    fn name(&self) -> &'static str {
+        "laravel"
+    }
+
+    fn is_entry_start(&self, text: &[u8]) -> bool {
+        self.match_date(text).0
+    }
+
+    fn parse_line<'a>(&self, source: &'a str) -> LogLine<'a> {
+        let date_len = match self.match_date(source.as_bytes()) {
+            (true, len) => len,
+            _ => 0,
+        };
+        let rest = &source[date_len..];
+
+        let level_len = rest.find(':').unwrap_or(rest.len());
+        let after_level = &rest[level_len..];
+        let text = after_level
+            .strip_prefix(": ")
+            .or_else(|| after_level.strip_prefix(':'))
+            .unwrap_or(after_level);
+        let line = LogLine::new(
+            source,
+            source[..date_len].trim_end(),
+            &rest[..level_len],
+            text,
+        );
+
+        match log_fields::split_trailing_json(text) {
+            Some((context, extra)) => {
+                line.with_fields(&text[context.0..context.1], &text[extra.0..extra.1])
+            }
+            None => line,
+        }
+    }
+}
+
+/// Monolog `JsonFormatter` output, one JSON object per line
+pub struct MonologJson {}
+
+impl MonologJson {
+    fn str_field<'v>(record: &'v Map<String, Value>, key: &str) -> &'v str {
+        record.get(key).and_then(Value::as_str).unwrap_or_default()
+    }
+
+    /// Context and extra are written after the message like `LineFormatter` does
+    fn append_json(text: &mut String, value: Option<&Value>) {
+        let value = match value {
+            Some(Value::Object(map)) if !map.is_empty() => value,
+            Some(Value::Array(arr)) if !arr.is_empty() => value,
+            _ => None,
+        };
+
+        if let Some(value) = value {
+            text.push(' ');
+            text.push_str(&value.to_string());
+        }
+    }
+}
+
+impl LogFormat for MonologJson {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn is_entry_start(&self, text: &[u8]) -> bool {
+        text.first() == Some(&b'{')
+    }
+
+    fn parse_line<'a>(&self, source: &'a str) -> LogLine<'a> {
+        let Ok(Value::Object(record)) = serde_json::from_str::<Value>(source.trim_end()) else {
+            return LogLine::new(source, &source[..0], &source[..0], source);
+        };
+
+        let date = format!("[{}]", Self::str_field(&record, "datetime"));
+        let log_level = format!(
+            "{}.{}",
+            Self::str_field(&record, "channel"),
+            Self::str_field(&record, "level_name")
+        );
+
+        let mut text = Self::str_field(&record, "message").to_owned();
+        Self::append_json(&mut text, record.get("context"));
+        Self::append_json(&mut text, record.get("extra"));
+        text.push('\n');
+
+        let json_field = |key| record.get(key).map_or("[]".to_owned(), Value::to_string);
+
+        LogLine::rewritten(
+            source,
+            &date,
+            &log_level,
+            &text,
+            &json_field("context"),
+            &json_field("extra"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn laravel_without_message() {
+        let line = LARAVEL.parse_line("[2023-02-14 13:43:49] local.INFO");
+
+        assert_eq!(line.date(), "[2023-02-14 13:43:49]");
+        assert_eq!(line.log_level(), "local.INFO");
+        assert_eq!(line.text(), "");
+    }
+
+    #[test]
+    fn laravel_multibyte_after_colon() {
+        let line = LARAVEL.parse_line("[2023-02-14 13:42:48] local.INFO:é x");
+        assert_eq!(line.log_level(), "local.INFO");
+        assert_eq!(line.text(), "é x");
+
+        let line = LARAVEL.parse_line("[2023-02-14 13:42:48] local.INFO: é");
+        assert_eq!(line.text(), "é");
+    }
+
+    #[test]
+    fn json_maps_monolog_fields() {
+        let line = MONOLOG_JSON.parse_line(
+            r#"{"message":"Incoming webhook","context":{"id":7},"level":200,"level_name":"INFO","channel":"local","datetime":"2023-02-14T13:42:48.123456+00:00","extra":{}}
+"#,
+        );
+
+        assert_eq!(line.date(), "[2023-02-14T13:42:48.123456+00:00]");
+        assert_eq!(line.log_level(), "local.INFO");
+        assert_eq!(line.text(), "Incoming webhook {\"id\":7}\n");
+        assert_eq!(line.slug(8), "Incoming");
+        assert_eq!(line.fields(), vec![("id".to_owned(), "7".to_owned())]);
+    }
+
+    #[test]
+    fn json_invalid_line_keeps_source() {
+        let line = MONOLOG_JSON.parse_line("{not json");
+
+        assert_eq!(line.text(), "{not json");
+        assert_eq!(line.log_level(), "");
+    }
+
+    #[test]
+    fn detect_format_from_first_entry() {
+        assert_eq!(detect("\n{\"message\":\"a\"}\n").name(), "json");
+        assert_eq!(
+            detect("[2023-02-14 13:42:48] local.INFO: a").name(),
+            "laravel"
+        );
+        assert_eq!(detect("").name(), "laravel");
+    }
+
+    #[test]
+    fn laravel_iso_dates() {
+        let line = LARAVEL.parse_line("[2024-05-01T12:00:00.123456+00:00] local.INFO: log1");
+        assert_eq!(line.date(), "[2024-05-01T12:00:00.123456+00:00]");
+        assert_eq!(line.log_level(), "local.INFO");
+        assert_eq!(line.text(), "log1");
+
+        assert_eq!(LARAVEL.match_date(b"[2024-05-01T12:00:00Z] a"), (true, 23));
+        assert_eq!(
+            LARAVEL.match_date(b"[2024-05-01T12:00:00-0130] a"),
+            (true, 27)
+        );
+        assert_eq!(LARAVEL.match_date(b"[2024-05-01 12:00:00.5] a"), (true, 24));
+        assert!(!LARAVEL.is_entry_start(b"[2024-05-01T12:00:00.] a"));
+        assert!(!LARAVEL.is_entry_start(b"[2024-05-01T12:00:00+01] a"));
+    }
+
+    #[test]
+    fn laravel_short_text_is_not_entry() {
+        assert!(!LARAVEL.is_entry_start(b"[2023-02-14"));
+        assert!(!LARAVEL.is_entry_start(b""));
+    }
+}
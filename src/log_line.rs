@@ -1,13 +1,17 @@
 use self_cell::self_cell;
 
-use crate::raw_parse;
+use crate::level::Level;
+use crate::log_fields::{self, Field};
+use crate::raw_parse::RawParser;
+use crate::timestamp::Timestamp;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct LogLines<'a>(pub Vec<LogLine<'a>>);
 
 // #[derive(Debug, PartialEq, Eq, Ord, PartialOrd)]
 self_cell!(
-    pub struct LogData {
+    /// Logs parsed from one read of the log file
+    pub struct LogChunk {
         owner: String,
         // pub time: &str,
 
@@ -18,91 +22,274 @@ self_cell!(
     impl {Debug, Eq, PartialEq}
 );
 
+impl LogChunk {
+    /// Byte offset of the last log, it might continue in the next read.
+    /// Without any logs the whole chunk is unfinished.
+    fn tail_start(&self) -> usize {
+        let owner = self.borrow_owner();
+        self.borrow_dependent().0.last().map_or(0, |line| {
+            line.source().as_ptr() as usize - owner.as_ptr() as usize
+        })
+    }
+}
+
+/// Parsed logs, appended text is stored in new chunks so earlier logs never have to be parsed again
+#[derive(Debug, Eq, PartialEq, Default)]
+pub struct LogData {
+    chunks: Vec<LogChunk>,
+    /// Index of the first log in each chunk
+    chunk_starts: Vec<usize>,
+    len: usize,
+}
+
+impl From<LogChunk> for LogData {
+    fn from(chunk: LogChunk) -> Self {
+        let mut data = LogData::empty();
+        data.push_chunk(chunk);
+        data
+    }
+}
+
 impl LogData {
     pub fn empty() -> Self {
-        LogData::new(String::new(), |_| LogLines(Vec::new()))
+        LogData::default()
     }
 
-    pub fn from_content(new_text: String) -> Self {
-        let parser = raw_parse::RawParser {};
+    pub fn from_content(parser: &RawParser, new_text: String) -> Self {
         let log_lines = parser.parse_lines(&new_text);
 
-        let ll = parser.map_log(new_text, log_lines.clone());
-        ll
+        parser.map_log(new_text, log_lines).into()
     }
 
-    pub fn append_text(self, new_text: &str) -> Self {
-        let mut owner = self.into_owner();
-        owner.push_str(new_text);
+    /// Parse `new_text` together with the last log, which may have been cut off by the previous read.
+    /// Returns the index of the first log that was added or parsed again.
+    pub fn append_text(&mut self, parser: &RawParser, new_text: &str) -> usize {
+        let Some(mut last) = self.chunks.pop() else {
+            *self = LogData::from_content(parser, new_text.to_owned());
+            return 0;
+        };
+        let last_start = self.chunk_starts.pop().unwrap_or(0);
+
+        let tail_start = last.tail_start();
+        let mut text = last.borrow_owner()[tail_start..].to_owned();
+        text.push_str(new_text);
+
+        // Drop the unfinished log, keep the rest of the chunk as is
+        last.with_dependent_mut(|_, lines| {
+            lines.0.pop();
+        });
+        self.len = last_start;
+        if !last.borrow_dependent().0.is_empty() {
+            self.push_chunk(last);
+        }
 
-        let parser = raw_parse::RawParser {};
-        let log_lines = parser.parse_lines(&owner);
+        let first_changed = self.len;
+        let log_lines = parser.parse_lines(&text);
+        self.push_chunk(parser.map_log(text, log_lines));
 
-        let ll = parser.map_log(owner, log_lines.clone());
-        ll
+        first_changed
+    }
+
+    /// Parse `new_text` on its own, e.g. from a new file after rotation. The last log is
+    /// finished and is not parsed again. Returns the index of the first added log.
+    pub fn append_chunk(&mut self, parser: &RawParser, new_text: &str) -> usize {
+        let first_added = self.len;
+        let log_lines = parser.parse_lines(new_text);
+        self.push_chunk(parser.map_log(new_text.to_owned(), log_lines));
+
+        first_added
+    }
+
+    fn push_chunk(&mut self, chunk: LogChunk) {
+        self.chunk_starts.push(self.len);
+        self.len += chunk.borrow_dependent().0.len();
+        self.chunks.push(chunk);
     }
 
     pub fn len(&self) -> usize {
-        self.log_lines().len()
+        self.len
     }
 
-    pub fn log_lines(&self) -> &Vec<LogLine> {
-        &self.borrow_dependent().0
+    pub fn get(&self, ix: usize) -> Option<&LogLine<'_>> {
+        let chunk = self.chunk_starts.partition_point(|start| *start <= ix);
+        let chunk_ix = chunk.checked_sub(1)?;
+
+        self.chunks[chunk_ix]
+            .borrow_dependent()
+            .0
+            .get(ix - self.chunk_starts[chunk_ix])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LogLine<'_>> + '_ {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.borrow_dependent().0.iter())
+    }
+}
+
+/// Byte range of a field in the entry, or in the rewritten fields of a JSON entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
+struct Span {
+    start: u32,
+    end: u32,
+}
+
+impl Span {
+    /// Where `field` is in `text`, `field` must be a slice of `text`
+    fn of(text: &str, field: &str) -> Self {
+        let start = field.as_ptr() as usize - text.as_ptr() as usize;
+        debug_assert!(start + field.len() <= text.len());
+
+        Span {
+            start: start as u32,
+            end: (start + field.len()) as u32,
+        }
+    }
+
+    fn get(self, text: &str) -> &str {
+        &text[self.start as usize..self.end as usize]
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Ord, PartialOrd)]
 pub struct LogLine<'a> {
     source: &'a str,
-    log_level_len: usize,
+    /// Fields that are not written as is in the source, like from a JSON record.
+    /// The spans point into this instead of the source when set.
+    rewritten: Option<Box<str>>,
+    date: Span,
+    /// Raw `channel.LEVEL`, e.g. `local.ERROR`
+    log_level: Span,
+    level: Option<Level>,
+    text: Span,
+    /// Raw JSON of the context and extra written after the message
+    context: Option<Span>,
+    extra: Option<Span>,
 }
 
 impl<'a> LogLine<'a> {
-    pub fn parse(source: &'a str) -> Self {
-        let ls = &source[22..].as_bytes();
+    /// Fields are slices of `source`
+    pub fn new(source: &'a str, date: &'a str, log_level: &'a str, text: &'a str) -> Self {
+        LogLine {
+            source,
+            rewritten: None,
+            date: Span::of(source, date),
+            log_level: Span::of(source, log_level),
+            level: Level::parse(split_log_level(log_level).1),
+            text: Span::of(source, text),
+            context: None,
+            extra: None,
+        }
+    }
 
-        let mut lg_len: usize = 0;
-        while let Some(c) = ls.get(lg_len) {
-            if *c == b':' {
-                break;
+    /// Fields that are not slices of `source`, context and extra are raw JSON
+    pub fn rewritten(
+        source: &'a str,
+        date: &str,
+        log_level: &str,
+        text: &str,
+        context: &str,
+        extra: &str,
+    ) -> Self {
+        let fields = [date, log_level, text, context, extra];
+        let mut buf = String::with_capacity(fields.iter().map(|f| f.len()).sum());
+        let spans = fields.map(|field| {
+            let start = buf.len();
+            buf.push_str(field);
+            Span {
+                start: start as u32,
+                end: buf.len() as u32,
             }
-
-            lg_len += 1;
-        }
+        });
 
         LogLine {
             source,
-            log_level_len: lg_len,
+            rewritten: Some(buf.into_boxed_str()),
+            date: spans[0],
+            log_level: spans[1],
+            level: Level::parse(split_log_level(log_level).1),
+            text: spans[2],
+            context: Some(spans[3]),
+            extra: Some(spans[4]),
         }
     }
 
+    /// Context and extra, slices of `source`
+    pub fn with_fields(mut self, context: &'a str, extra: &'a str) -> Self {
+        self.context = Some(Span::of(self.source, context));
+        self.extra = Some(Span::of(self.source, extra));
+        self
+    }
+
+    /// The raw entry as it was read from the log file
+    #[allow(dead_code)]
+    pub fn source(&self) -> &str {
+        self.source
+    }
+
+    /// Text the field spans point into
+    fn fields_text(&self) -> &str {
+        self.rewritten.as_deref().unwrap_or(self.source)
+    }
+
     pub fn text(&self) -> &str {
-        let ix = 22 + self.log_level_len + 2;
-        &self.source[ix..]
+        self.text.get(self.fields_text())
     }
 
     pub fn slug(&self, slug_len: usize) -> &str {
-        let ix = 22 + self.log_level_len + 2;
-        let end = usize::min(self.source.len(), ix + slug_len);
-        &self.source[ix..end]
+        let text = self.text();
+        let mut end = usize::min(text.len(), slug_len);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        &text[..end]
     }
 
-    pub fn info(&self) -> &str {
-        let ix = 22 + self.log_level_len + 2;
-        &self.source[0..ix]
+    pub fn info(&self) -> String {
+        format!("{} {}: ", self.date(), self.log_level())
     }
 
     #[allow(dead_code)]
     pub fn date(&self) -> &str {
-        &self.source[0..21]
+        self.date.get(self.fields_text())
+    }
+
+    /// Parsed from the date when needed, most logs are never filtered by time
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        Timestamp::parse(self.date())
     }
 
     #[allow(dead_code)]
     pub fn log_level(&self) -> &str {
-        &self.source[22..22 + self.log_level_len]
+        self.log_level.get(self.fields_text())
+    }
+
+    pub fn level(&self) -> Option<Level> {
+        self.level
+    }
+
+    /// Channel or environment, `local` in `local.ERROR`
+    pub fn channel(&self) -> &str {
+        split_log_level(self.log_level()).0
+    }
+
+    /// Context and extra as key/value pairs, extra keys are prefixed with `extra.`
+    pub fn fields(&self) -> Vec<Field> {
+        let parse = |json: Option<Span>| {
+            json.and_then(|span| log_fields::parse_lenient(span.get(self.fields_text())))
+        };
+
+        let mut fields = log_fields::to_fields(parse(self.context), "");
+        fields.extend(log_fields::to_fields(parse(self.extra), "extra."));
+        fields
     }
 }
 
+/// Split `local.ERROR` into channel and level
+fn split_log_level(log_level: &str) -> (&str, &str) {
+    log_level.rsplit_once('.').unwrap_or(("", log_level))
+}
+
 #[cfg(test)]
 mod tests {
     // use crate::log_line::LogLine;
@@ -116,15 +303,111 @@ mod tests {
 [2023-02-14 13:43:50] local.ERROR: log2
 ";
 
-        let mut data = LogData::from_content(short_log.to_owned());
+        let parser = RawParser::default();
+        let mut data = LogData::from_content(&parser, short_log.to_owned());
+
+        assert_eq!(data.get(0).unwrap().text(), "log1\n");
+        assert_eq!(data.get(1).unwrap().text(), "log2\n");
+
+        let first_changed = data.append_text(&parser, "[2023-02-14 13:42:48] local.INFO: log3\n");
+
+        assert_eq!(first_changed, 1);
+        assert_eq!(data.get(0).unwrap().text(), "log1\n");
+        assert_eq!(data.get(1).unwrap().text(), "log2\n");
+        assert_eq!(data.get(2).unwrap().text(), "log3\n");
+        assert_eq!(data.get(3), None);
+        assert_eq!(data.len(), 3);
+    }
+
+    #[test]
+    fn append_log_split_between_reads() {
+        let parser = RawParser::default();
+        let mut data =
+            LogData::from_content(&parser, "[2023-02-14 13:42:48] local.INFO: lo".to_owned());
+
+        assert_eq!(
+            data.append_text(&parser, "g1\n#0 /app/Foo.php(1)\n[2023-02-14 13:4"),
+            0
+        );
+        // Unfinished date can't be told apart from the trace yet
+        assert_eq!(data.len(), 1);
 
-        assert_eq!(data.log_lines()[0].text(), "log1\n");
-        assert_eq!(data.log_lines()[1].text(), "log2\n");
+        assert_eq!(data.append_text(&parser, "3:50] local.ERROR: log2\n"), 0);
+        assert_eq!(data.len(), 2);
+        assert_eq!(data.get(1).unwrap().date(), "[2023-02-14 13:43:50]");
+        assert_eq!(data.get(1).unwrap().text(), "log2\n");
+
+        let texts: Vec<_> = data.iter().map(|l| l.text()).collect();
+        assert_eq!(texts, vec!["log1\n#0 /app/Foo.php(1)\n", "log2\n"]);
+    }
+
+    #[test]
+    fn append_chunk_after_unfinished_log() {
+        let parser = RawParser::default();
+        let mut data =
+            LogData::from_content(&parser, "[2023-02-14 13:42:48] local.INFO: old".to_owned());
+
+        assert_eq!(
+            data.append_chunk(&parser, "[2023-02-14 13:42:49] local.INFO: new"),
+            1
+        );
+        assert_eq!(data.append_text(&parser, " line\n"), 1);
+
+        let texts: Vec<_> = data.iter().map(|l| l.text()).collect();
+        assert_eq!(texts, vec!["old", "new line\n"]);
+    }
+
+    #[test]
+    fn append_to_empty() {
+        let parser = RawParser::default();
+        let mut data = LogData::empty();
+
+        assert_eq!(data.append_text(&parser, "[2023-02-14 13:4"), 0);
+        assert_eq!(data.len(), 0);
+
+        assert_eq!(data.append_text(&parser, "2:48] local.INFO: log1\n"), 0);
+        assert_eq!(data.get(0).unwrap().text(), "log1\n");
+    }
+
+    #[test]
+    fn fields_from_context_and_extra() {
+        let short_log: &str =
+            "[2023-02-14 13:42:48] local.INFO: log1 {\"userId\":42} {\"ip\":\"::1\"}
+[2023-02-14 13:43:50] local.ERROR: log2 [] []
+[2023-02-14 13:43:50] local.ERROR: log3
+";
+
+        let data = LogData::from_content(&RawParser::default(), short_log.to_owned());
+
+        let lines: Vec<_> = data.iter().collect();
+
+        assert_eq!(
+            lines[0].fields(),
+            vec![
+                ("userId".to_owned(), "42".to_owned()),
+                ("extra.ip".to_owned(), "::1".to_owned())
+            ]
+        );
+        assert_eq!(lines[0].text(), "log1 {\"userId\":42} {\"ip\":\"::1\"}\n");
+        assert_eq!(lines[1].fields(), vec![]);
+        assert_eq!(lines[2].fields(), vec![]);
+    }
+
+    #[test]
+    fn level_and_channel() {
+        let short_log: &str = "[2023-02-14 13:42:48] local.warn: log1
+[2023-02-14 13:43:50] production.EMERGENCY: log2
+[2023-02-14 13:43:50] apple: log3
+";
 
-        data = data.append_text("[2023-02-14 13:42:48] local.INFO: log3\n");
+        let data = LogData::from_content(&RawParser::default(), short_log.to_owned());
+        let lines: Vec<_> = data.iter().collect();
 
-        assert_eq!(data.log_lines()[0].text(), "log1\n");
-        assert_eq!(data.log_lines()[1].text(), "log2\n");
-        assert_eq!(data.log_lines()[2].text(), "log3\n");
+        assert_eq!(lines[0].level(), Some(Level::Warning));
+        assert_eq!(lines[0].channel(), "local");
+        assert_eq!(lines[1].level(), Some(Level::Emergency));
+        assert_eq!(lines[1].channel(), "production");
+        assert_eq!(lines[2].level(), None);
+        assert_eq!(lines[2].channel(), "");
     }
 }
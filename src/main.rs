@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use chrono::Local;
 use clap::Parser;
 use crossterm::{
     // event::{DisableMouseCapture, EnableMouseCapture},
@@ -14,6 +15,7 @@ use std::{
     error::Error,
     fs::{metadata, File},
     io::{self, Read},
+    path::PathBuf,
     process::exit,
     time::Instant,
 };
@@ -21,19 +23,35 @@ use std::{
 use app_data::App;
 
 use crate::app_data::FileInfo;
+use crate::config::{Config, PathMapping};
+use crate::time_range::{parse_bound, TimeRange};
+use crate::view_state::ViewState;
 
+mod level;
+mod log_fields;
+mod log_format;
 mod log_line;
+mod pager;
 mod app_data;
+mod config;
+mod editor;
+mod events;
+mod highlight;
+mod query;
 mod raw_parse;
+mod search;
+mod stack_trace;
 mod stateful_list;
+mod time_range;
+mod timestamp;
+mod trace_view;
 mod ui;
+mod view_state;
+mod watcher;
 
 
 // # Planned and missing features:
-// - Show enabled modes and status in status bar
-// - Scroll bar for logs...
 // - Group duplicated messages
-// - Add focus modes. Log list only, Log text only, Both side by side.
 
 
 /// Laravel log reader
@@ -43,6 +61,32 @@ struct Args {
     /// Log file to read from
     #[arg(default_value = "./storage/log/laravel.log")]
     log_path: String,
+
+    /// Log format, guessed from the first entry when set to auto
+    #[arg(long, default_value = "auto", value_parser = ["auto", "laravel", "json"])]
+    format: String,
+
+    /// Config file to use instead of ~/.config/termilog/config.toml
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Map paths in the logs to local paths when opening them in $EDITOR,
+    /// e.g. --path-map /var/www/html=/home/me/app
+    #[arg(long, value_name = "FROM=TO", value_parser = PathMapping::parse)]
+    path_map: Vec<PathMapping>,
+
+    /// Only show logs since a time or duration ago, e.g. 1h or "2023-02-14 13:00"
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Only show logs until a time or duration ago
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Check the file for changes on an interval, for mounts where
+    /// filesystem notifications never arrive
+    #[arg(long)]
+    poll: bool,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -51,6 +95,19 @@ fn main() -> Result<(), Box<dyn Error>> {
     info!("Starting up!");
     let args = Args::parse();
 
+    let config_path = args.config.as_ref().map(PathBuf::from);
+    let mut config = match config_path.or_else(Config::default_path) {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+    config.path_map.extend(args.path_map);
+
+    let local_now = Local::now();
+    let time_range = TimeRange {
+        since: args.since.map(|s| parse_bound(&s, local_now)).transpose()?,
+        until: args.until.map(|s| parse_bound(&s, local_now)).transpose()?,
+    };
+
     let now = Instant::now();
 
     let meta = metadata(&args.log_path).expect("Failed to get meta data from path");
@@ -68,14 +125,16 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Read file: {}ms", now.elapsed().as_millis());
     let now = Instant::now();
 
-    let parser = raw_parse::RawParser {};
+    let format = log_format::by_name(&args.format).unwrap_or_else(|| log_format::detect(&contents));
+    let parser = raw_parse::RawParser::new(format);
     let log_lines = parser.parse_lines(&contents);
 
-    let ll = parser.map_log(contents.clone(), log_lines.clone());
+    let number_of_lines = log_lines.len();
+    let ll = parser.map_log(contents, log_lines).into();
 
     println!(
         "Number of lines: {} in {}ms",
-        log_lines.len(),
+        number_of_lines,
         now.elapsed().as_millis()
     );
 
@@ -91,13 +150,19 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let app = App::new(
+    let mut app = App::new(
         FileInfo {
             name: args.log_path,
             size: file_size,
+            missing: false,
         },
+        parser,
         ll,
+        config,
+        ViewState::default_path(),
     );
+    app.set_time_range(time_range);
+    app.set_poll_file(args.poll);
     let res = app.run_app(&mut terminal);
 
     // restore terminal
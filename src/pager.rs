@@ -0,0 +1,480 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::highlight::Highlighter;
+use crate::search::{Search, MATCH_COLOR};
+use crate::ui;
+
+const CURSOR_STYLE: Style = Style::new().add_modifier(Modifier::REVERSED);
+const SELECTION_STYLE: Style = Style::new().fg(Color::Black).bg(Color::Gray);
+const GUTTER_STYLE: Style = Style::new().fg(Color::Gray);
+
+/// Read only view of the selected log with a cursor, paging, wrapping and selection
+#[derive(Debug, Default)]
+pub struct Pager {
+    lines: Vec<String>,
+    /// Row and char column
+    cursor: (usize, usize),
+    /// Where the selection started, it ends at the cursor
+    selection: Option<(usize, usize)>,
+    /// First shown line
+    top: usize,
+    /// First shown column when not wrapping
+    left: usize,
+    wrap: bool,
+    search: Option<Search>,
+    /// Number of text rows in the last render, used for paging
+    height: usize,
+}
+
+impl Pager {
+    pub fn new(mut lines: Vec<String>) -> Self {
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        Pager {
+            lines,
+            ..Pager::default()
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn is_wrapping(&self) -> bool {
+        self.wrap
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.left = 0;
+    }
+
+    /// Handle a navigation key, returns false for keys the pager doesn't use
+    pub fn input(&mut self, key: KeyEvent) -> bool {
+        if key.kind != KeyEventKind::Press {
+            return false;
+        }
+
+        let (row, col) = self.cursor;
+        let page = self.height.max(1);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(row.saturating_sub(1), col),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(row + 1, col),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(row, col.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(row, col + 1),
+            KeyCode::PageUp => self.move_cursor(row.saturating_sub(page), col),
+            KeyCode::PageDown => self.move_cursor(row + page, col),
+            KeyCode::Home | KeyCode::Char('0') => self.move_cursor(row, 0),
+            KeyCode::End | KeyCode::Char('$') => self.move_cursor(row, usize::MAX),
+            KeyCode::Char('g') => self.move_cursor(0, 0),
+            KeyCode::Char('G') => self.move_cursor(usize::MAX, 0),
+            KeyCode::Char('w') => self.toggle_wrap(),
+            KeyCode::Char('v') => self.toggle_selection(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Move the cursor, clamped to the text
+    pub fn move_cursor(&mut self, row: usize, col: usize) {
+        let row = row.min(self.lines.len() - 1);
+        let col = col.min(self.lines[row].chars().count());
+        self.cursor = (row, col);
+    }
+
+    pub fn toggle_selection(&mut self) {
+        self.selection = match self.selection {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    /// Selected range from start to end, the char under the cursor is included
+    fn selection_range(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.selection?;
+        let (start, end) = if anchor <= self.cursor {
+            (anchor, self.cursor)
+        } else {
+            (self.cursor, anchor)
+        };
+
+        Some((start, (end.0, end.1 + 1)))
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection_range()?;
+
+        let mut text = String::new();
+        for row in start.0..=end.0 {
+            let line = &self.lines[row];
+            let from = if row == start.0 { start.1 } else { 0 };
+            let to = if row == end.0 { end.1 } else { usize::MAX };
+
+            if row > start.0 {
+                text.push('\n');
+            }
+            text.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+        }
+
+        Some(text)
+    }
+
+    pub fn search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    pub fn set_search(&mut self, search: Option<Search>) {
+        self.search = search;
+    }
+
+    /// Move to the next hit, or the hit under the cursor with `match_cursor`. Wraps around.
+    pub fn search_forward(&mut self, match_cursor: bool) -> bool {
+        let Some(search) = &self.search else {
+            return false;
+        };
+
+        let hits = search.find_in_lines(&self.lines);
+        let next = hits
+            .iter()
+            .find(|hit| **hit > self.cursor || (match_cursor && **hit == self.cursor))
+            .or(hits.first());
+
+        match next {
+            Some(hit) => {
+                self.cursor = *hit;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move to the previous hit, wraps around
+    pub fn search_back(&mut self) -> bool {
+        let Some(search) = &self.search else {
+            return false;
+        };
+
+        let hits = search.find_in_lines(&self.lines);
+        let previous = hits
+            .iter()
+            .rev()
+            .find(|hit| **hit < self.cursor)
+            .or(hits.last());
+
+        match previous {
+            Some(hit) => {
+                self.cursor = *hit;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of the hit under the cursor and the total number of hits
+    pub fn search_position(&self) -> Option<(Option<usize>, usize)> {
+        let hits = self.search.as_ref()?.find_in_lines(&self.lines);
+        let current = hits.binary_search(&self.cursor).ok();
+
+        Some((current.map(|ix| ix + 1), hits.len()))
+    }
+
+    /// Rows used by `row` when wrapped at `width`, a cursor past the end of the line needs a cell too
+    fn wrapped_height(&self, row: usize, width: usize) -> usize {
+        let mut cells = self.lines[row].chars().count();
+        if row == self.cursor.0 {
+            cells = cells.max(self.cursor.1 + 1);
+        }
+        cells.div_ceil(width).max(1)
+    }
+
+    /// Scroll so the cursor is inside the shown text
+    fn scroll_to_cursor(&mut self, width: usize) {
+        let (row, col) = self.cursor;
+        let height = self.height.max(1);
+
+        if row < self.top {
+            self.top = row;
+        } else if self.wrap {
+            let mut used: usize = (self.top..=row)
+                .map(|r| self.wrapped_height(r, width))
+                .sum();
+            while used > height && self.top < row {
+                used -= self.wrapped_height(self.top, width);
+                self.top += 1;
+            }
+        } else if row >= self.top + height {
+            self.top = row + 1 - height;
+        }
+
+        if !self.wrap {
+            if col < self.left {
+                self.left = col;
+            } else if col >= self.left + width {
+                self.left = col + 1 - width;
+            }
+        }
+    }
+
+    /// Style of each char in `row`, plus one past the end for the cursor
+    fn char_styles(&self, row: usize, show_cursor: bool, highlighter: &Highlighter) -> Vec<Style> {
+        let line = &self.lines[row];
+        let mut styles = vec![Style::default(); line.chars().count() + 1];
+
+        highlighter.apply(line, &mut styles);
+        if let Some(search) = &self.search {
+            search.apply(line, &mut styles);
+        }
+
+        if let Some((start, end)) = self.selection_range() {
+            if (start.0..=end.0).contains(&row) {
+                let from = if row == start.0 { start.1 } else { 0 };
+                let to = if row == end.0 { end.1 } else { styles.len() };
+                for style in styles.iter_mut().take(to).skip(from) {
+                    *style = style.patch(SELECTION_STYLE);
+                }
+            }
+        }
+
+        if show_cursor && row == self.cursor.0 {
+            styles[self.cursor.1] = styles[self.cursor.1].patch(CURSOR_STYLE);
+        }
+
+        styles
+    }
+
+    /// Spans for the chars `from..to` of `row`, the char past the end is only shown for the cursor
+    fn spans(&self, row: usize, from: usize, to: usize, styles: &[Style]) -> Vec<Span<'static>> {
+        let line = &self.lines[row];
+        let end_of_line =
+            (row == self.cursor.0 && self.cursor.1 == styles.len() - 1).then_some(' ');
+        let chars = line.chars().chain(end_of_line);
+
+        let mut spans: Vec<Span> = Vec::new();
+        let mut text = String::new();
+        let mut style = Style::default();
+        for (ix, c) in chars.enumerate().skip(from).take(to - from) {
+            if styles[ix] != style && !text.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut text), style));
+            }
+            style = styles[ix];
+            // Tabs would throw off the cursor position
+            text.push(if c == '\t' { ' ' } else { c });
+        }
+        if !text.is_empty() {
+            spans.push(Span::styled(text, style));
+        }
+
+        spans
+    }
+
+    pub fn render(
+        &mut self,
+        f: &mut Frame,
+        area: Rect,
+        block: Block,
+        show_cursor: bool,
+        highlighter: &Highlighter,
+    ) {
+        let inner = block.inner(area);
+        let gutter_width = self.lines.len().to_string().len() + 1;
+        let width = (inner.width as usize).saturating_sub(gutter_width).max(1);
+        self.height = inner.height as usize;
+        self.scroll_to_cursor(width);
+
+        let mut rows: Vec<Line> = Vec::new();
+        for row in self.top..self.lines.len() {
+            if rows.len() >= self.height {
+                break;
+            }
+
+            let styles = self.char_styles(row, show_cursor, highlighter);
+            let gutter = format!("{:>1$} ", row + 1, gutter_width - 1);
+            if self.wrap {
+                for part in 0..self.wrapped_height(row, width) {
+                    if rows.len() >= self.height {
+                        break;
+                    }
+
+                    let from = part * width;
+                    let to = (from + width).min(styles.len());
+                    let gutter = if from == 0 {
+                        gutter.clone()
+                    } else {
+                        " ".repeat(gutter_width)
+                    };
+
+                    let mut spans = vec![Span::styled(gutter, GUTTER_STYLE)];
+                    spans.extend(self.spans(row, from, to, &styles));
+                    rows.push(Line::from(spans));
+                }
+            } else {
+                let from = self.left.min(styles.len());
+                let to = (self.left + width).min(styles.len());
+
+                let mut spans = vec![Span::styled(gutter, GUTTER_STYLE)];
+                spans.extend(self.spans(row, from, to, &styles));
+                rows.push(Line::from(spans));
+            }
+        }
+
+        f.render_widget(Paragraph::new(rows).block(block), area);
+
+        if self.lines.len() > self.height {
+            let mut hit_rows: Vec<usize> = match &self.search {
+                Some(search) => search
+                    .find_in_lines(&self.lines)
+                    .into_iter()
+                    .map(|(row, _)| row)
+                    .collect(),
+                None => Vec::new(),
+            };
+            hit_rows.dedup();
+
+            ui::render_scrollbar(
+                f,
+                area,
+                self.lines.len(),
+                self.cursor.0,
+                &[(&hit_rows, MATCH_COLOR)],
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    fn pager(text: &str) -> Pager {
+        Pager::new(text.lines().map(String::from).collect())
+    }
+
+    fn press(pager: &mut Pager, code: KeyCode) -> bool {
+        pager.input(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn cursor_stays_in_text() {
+        let mut pager = pager("first line\nsecond\n");
+
+        press(&mut pager, KeyCode::End);
+        assert_eq!(pager.cursor(), (0, 10));
+
+        press(&mut pager, KeyCode::Down);
+        assert_eq!(pager.cursor(), (1, 6));
+
+        press(&mut pager, KeyCode::PageDown);
+        assert_eq!(pager.cursor(), (1, 6));
+
+        press(&mut pager, KeyCode::Char('g'));
+        assert_eq!(pager.cursor(), (0, 0));
+    }
+
+    #[test]
+    fn keys_never_change_text() {
+        let mut pager = pager("log text");
+
+        for c in "xdiu \n".chars() {
+            assert!(!press(&mut pager, KeyCode::Char(c)));
+        }
+        assert!(!press(&mut pager, KeyCode::Backspace));
+        assert!(!press(&mut pager, KeyCode::Delete));
+
+        assert_eq!(pager.lines(), ["log text"]);
+    }
+
+    #[test]
+    fn select_text() {
+        let mut pager = pager("first line\nsecond\nthird");
+        pager.move_cursor(0, 6);
+        press(&mut pager, KeyCode::Char('v'));
+        pager.move_cursor(1, 2);
+
+        assert_eq!(pager.selected_text(), Some("line\nsec".to_owned()));
+
+        // Selecting backwards
+        pager.move_cursor(0, 0);
+        assert_eq!(pager.selected_text(), Some("first l".to_owned()));
+
+        press(&mut pager, KeyCode::Char('v'));
+        assert_eq!(pager.selected_text(), None);
+    }
+
+    #[test]
+    fn search_hits_wrap_around() {
+        let mut pager = pager("an error\nok\nerror again");
+        pager.set_search(Search::new("error").ok());
+
+        assert!(pager.search_forward(true));
+        assert_eq!(pager.cursor(), (0, 3));
+        assert_eq!(pager.search_position(), Some((Some(1), 2)));
+
+        pager.search_forward(false);
+        assert_eq!(pager.cursor(), (2, 0));
+
+        pager.search_forward(false);
+        assert_eq!(pager.cursor(), (0, 3));
+
+        pager.search_back();
+        assert_eq!(pager.cursor(), (2, 0));
+    }
+
+    #[test]
+    fn scroll_to_cursor() {
+        let mut pager = pager("0123456789\n1\n2\n3\n4");
+        pager.height = 2;
+
+        pager.move_cursor(3, 0);
+        pager.scroll_to_cursor(4);
+        assert_eq!(pager.top, 2);
+
+        pager.move_cursor(0, 7);
+        pager.scroll_to_cursor(4);
+        assert_eq!((pager.top, pager.left), (0, 4));
+
+        // Wrapped the first line takes 3 rows
+        pager.toggle_wrap();
+        pager.move_cursor(1, 0);
+        pager.scroll_to_cursor(4);
+        assert_eq!(pager.top, 1);
+    }
+
+    #[test]
+    fn render_with_gutter() {
+        let mut pager = pager("first line\nsecond");
+        pager.move_cursor(1, 6);
+        pager.toggle_wrap();
+
+        let backend = ratatui::backend::TestBackend::new(8, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| {
+                let highlighter = Highlighter::new(&[]);
+                pager.render(f, f.area(), Block::default(), true, &highlighter)
+            })
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..4)
+            .map(|y| (0..8).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect();
+        assert_eq!(rows, ["1 first ", "  line  ", "2 second", "        "]);
+        assert_eq!(buffer[(0, 3)].style().add_modifier, Modifier::empty());
+        // "second" fills the row, the cursor after it gets a row of its own
+        assert_eq!(buffer[(2, 3)].style().add_modifier, Modifier::REVERSED);
+    }
+}
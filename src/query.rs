@@ -0,0 +1,589 @@
+//! Filter expressions like
+//! `level>=warning and channel:local and text~"webhook" and not text~healthcheck`.
+//!
+//! A bare word or string is a regex matched against the text. Terms next to
+//! each other are joined with `and`.
+//!
+//! A line is only parsed as a query when it uses a known field like `level>=`
+//! or a `name:value` predicate written as one word, anything else is a plain
+//! regex on the whole line so `webhook: 7` or `not found` keep working.
+
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::LazyLock;
+
+use chrono::NaiveDateTime;
+use regex::{Regex, RegexBuilder};
+
+use crate::level::Level;
+use crate::log_fields::Field;
+use crate::log_line::LogLine;
+use crate::search::regex_error_reason;
+use crate::timestamp::parse_user_time;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// Byte offset in the query where the error is
+    pub pos: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// `:` or `=`
+    Eq,
+    NotEq,
+    Match,
+    NotMatch,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Op {
+    fn matches(&self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering.is_eq(),
+            Op::NotEq => ordering.is_ne(),
+            Op::Gt => ordering.is_gt(),
+            Op::Gte => ordering.is_ge(),
+            Op::Lt => ordering.is_lt(),
+            Op::Lte => ordering.is_le(),
+            Op::Match | Op::NotMatch => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(Op),
+    LParen,
+    RParen,
+}
+
+fn is_op_char(c: char) -> bool {
+    matches!(c, ':' | '=' | '!' | '~' | '<' | '>')
+}
+
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+
+    while let Some(&(pos, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push((pos, Token::LParen));
+        } else if c == ')' {
+            chars.next();
+            tokens.push((pos, Token::RParen));
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            let mut closed = false;
+            while let Some((_, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        closed = true;
+                        break;
+                    }
+                    // Only quotes are escaped, other backslashes are kept for the regex
+                    '\\' if chars.peek().is_some_and(|(_, c)| *c == '"') => {
+                        text.push('"');
+                        chars.next();
+                    }
+                    c => text.push(c),
+                }
+            }
+
+            if !closed {
+                return Err(QueryError {
+                    pos,
+                    message: "unclosed string".to_owned(),
+                });
+            }
+            tokens.push((pos, Token::Str(text)));
+        } else if is_op_char(c) {
+            let mut op = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !is_op_char(c) {
+                    break;
+                }
+                op.push(c);
+                chars.next();
+            }
+
+            let op = match op.as_str() {
+                ":" | "=" | "==" => Op::Eq,
+                "!=" => Op::NotEq,
+                "~" => Op::Match,
+                "!~" => Op::NotMatch,
+                ">" => Op::Gt,
+                ">=" => Op::Gte,
+                "<" => Op::Lt,
+                "<=" => Op::Lte,
+                _ => {
+                    return Err(QueryError {
+                        pos,
+                        message: format!("unknown operator '{}'", op),
+                    })
+                }
+            };
+            tokens.push((pos, Token::Op(op)));
+        } else {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' || c == '"' || is_op_char(c) {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push((pos, Token::Word(word)));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+enum Predicate {
+    /// Bare word, matched against the text and level
+    Text(Regex),
+    Level(Op, Level),
+    Channel(Op, String),
+    ChannelRegex(Op, Regex),
+    TextContains(Op, String),
+    TextRegex(Op, Regex),
+    Time(Op, NaiveDateTime),
+    /// Prefix of the date as written in the log, `time:2023-02-14`
+    TimePrefix(String),
+    Field(String, Op, String),
+    FieldRegex(String, Op, Regex),
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Predicate(Predicate),
+}
+
+/// Parsed filter query
+#[derive(Debug)]
+pub struct Query {
+    expr: Expr,
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    tokens: Vec<(usize, Token)>,
+    ix: usize,
+}
+
+fn is_keyword(token: &Token, keyword: &str) -> bool {
+    matches!(token, Token::Word(w) if w.eq_ignore_ascii_case(keyword))
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.ix).map(|(_, t)| t)
+    }
+
+    fn pos(&self) -> usize {
+        self.tokens
+            .get(self.ix)
+            .map_or(self.query.len(), |(pos, _)| *pos)
+    }
+
+    fn error<T>(&self, pos: usize, message: impl Into<String>) -> Result<T, QueryError> {
+        Err(QueryError {
+            pos,
+            message: message.into(),
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.ix).map(|(_, t)| t.clone());
+        self.ix += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_and()?;
+        while self.peek().is_some_and(|t| is_keyword(t, "or")) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(t) if is_keyword(t, "and") => {
+                    self.next();
+                }
+                // Terms next to each other are and:ed
+                Some(t) if !is_keyword(t, "or") && *t != Token::RParen => {}
+                _ => break,
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        let pos = self.pos();
+        match self.peek() {
+            Some(t) if is_keyword(t, "not") => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.next();
+                let expr = self.parse_or()?;
+                if self.next() != Some(Token::RParen) {
+                    return self.error(pos, "unclosed '('");
+                }
+                Ok(expr)
+            }
+            Some(Token::Word(_)) | Some(Token::Str(_)) => self.parse_predicate(),
+            Some(Token::RParen) => self.error(pos, "unexpected ')'"),
+            Some(Token::Op(_)) => self.error(pos, "expected field before operator"),
+            None => self.error(pos, "expected expression"),
+        }
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr, QueryError> {
+        let name_pos = self.pos();
+        let (name, quoted) = match self.next() {
+            Some(Token::Word(w)) => (w, false),
+            Some(Token::Str(s)) => (s, true),
+            _ => return self.error(name_pos, "expected field or text"),
+        };
+
+        let op = match self.peek() {
+            Some(Token::Op(op)) if !quoted => *op,
+            _ => {
+                let re = self.regex(name_pos, &name)?;
+                return Ok(Expr::Predicate(Predicate::Text(re)));
+            }
+        };
+        self.next();
+
+        let value_pos = self.pos();
+        let value = match self.next() {
+            Some(Token::Word(w)) | Some(Token::Str(w)) => w,
+            _ => return self.error(value_pos, "expected value"),
+        };
+
+        let is_match = matches!(op, Op::Match | Op::NotMatch);
+        let is_eq = matches!(op, Op::Eq | Op::NotEq);
+        let predicate = match name.to_ascii_lowercase().as_str() {
+            "level" if !is_match => match Level::parse(&value) {
+                Some(level) => Predicate::Level(op, level),
+                None => return self.error(value_pos, format!("unknown level '{}'", value)),
+            },
+            "channel" | "env" if is_match => {
+                Predicate::ChannelRegex(op, self.regex(value_pos, &value)?)
+            }
+            "channel" | "env" if is_eq => Predicate::Channel(op, value),
+            "text" | "message" | "msg" if is_match => {
+                Predicate::TextRegex(op, self.regex(value_pos, &value)?)
+            }
+            "text" | "message" | "msg" if is_eq => {
+                Predicate::TextContains(op, value.to_lowercase())
+            }
+            "time" | "date" if op == Op::Eq => Predicate::TimePrefix(value),
+            "time" | "date" if !is_match => match parse_user_time(&value) {
+                Some(time) => Predicate::Time(op, time),
+                None => return self.error(value_pos, "expected time like \"2023-02-14 13:00\""),
+            },
+            "level" | "channel" | "env" | "text" | "message" | "msg" | "time" | "date" => {
+                return self.error(name_pos, format!("operator not supported for {}", name))
+            }
+            _ if is_match => Predicate::FieldRegex(name, op, self.regex(value_pos, &value)?),
+            _ => Predicate::Field(name, op, value),
+        };
+
+        Ok(Expr::Predicate(predicate))
+    }
+
+    fn regex(&self, pos: usize, pattern: &str) -> Result<Regex, QueryError> {
+        build_regex(pos, pattern)
+    }
+}
+
+fn build_regex(pos: usize, pattern: &str) -> Result<Regex, QueryError> {
+    RegexBuilder::new(pattern)
+        .build()
+        .map_err(|err| QueryError {
+            pos,
+            message: regex_error_reason(&err),
+        })
+}
+
+/// A known field followed by an operator, e.g. `level>=`, but not a `Message::` call
+static KNOWN_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:^|[\s(])(?:level|channel|env|text|message|msg|time|date)\s*[:=!~<>](?:[^:]|$)",
+    )
+    .expect("valid regex")
+});
+
+/// A predicate like `userId:42` without spaces around a single operator
+static ATTACHED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[\s(])[A-Za-z_](?:[\w.-]*[\w.])?(?:!=|!~|<=|>=|[:=~<>])[^\s:=!~<>/]")
+        .expect("valid regex")
+});
+
+/// Uses a known field, or a predicate like `userId:42`. `Foo::bar` or `a->b` are plain text.
+fn looks_like_query(query: &str) -> bool {
+    KNOWN_FIELD.is_match(query) || ATTACHED.is_match(query)
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        if !looks_like_query(query) {
+            let re = build_regex(0, query)?;
+            return Ok(Query {
+                expr: Expr::Predicate(Predicate::Text(re)),
+            });
+        }
+
+        let mut parser = Parser {
+            query,
+            tokens: tokenize(query)?,
+            ix: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if parser.ix < parser.tokens.len() {
+            return parser.error(parser.pos(), "unexpected ')'");
+        }
+
+        Ok(Query { expr })
+    }
+
+    pub fn matches(&self, log: &LogLine) -> bool {
+        let ctx = EvalContext {
+            log,
+            fields: OnceCell::new(),
+        };
+        ctx.eval(&self.expr)
+    }
+}
+
+struct EvalContext<'l, 'a> {
+    log: &'l LogLine<'a>,
+    /// Only parsed if the query uses fields
+    fields: OnceCell<Vec<Field>>,
+}
+
+impl EvalContext<'_, '_> {
+    fn eval(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::And(a, b) => self.eval(a) && self.eval(b),
+            Expr::Or(a, b) => self.eval(a) || self.eval(b),
+            Expr::Not(e) => !self.eval(e),
+            Expr::Predicate(p) => self.eval_predicate(p),
+        }
+    }
+
+    fn eval_predicate(&self, predicate: &Predicate) -> bool {
+        let log = self.log;
+        match predicate {
+            Predicate::Text(re) => re.is_match(log.log_level()) || re.is_match(log.text()),
+            Predicate::Level(op, level) => log.level().is_some_and(|l| op.matches(l.cmp(level))),
+            Predicate::Channel(op, channel) => {
+                (op == &Op::Eq) == log.channel().eq_ignore_ascii_case(channel)
+            }
+            Predicate::ChannelRegex(op, re) => (op == &Op::Match) == re.is_match(log.channel()),
+            Predicate::TextContains(op, text) => {
+                (op == &Op::Eq) == log.text().to_lowercase().contains(text)
+            }
+            Predicate::TextRegex(op, re) => (op == &Op::Match) == re.is_match(log.text()),
+            Predicate::Time(op, time) => log
+                .timestamp()
+                .is_some_and(|ts| op.matches(ts.local.cmp(time))),
+            Predicate::TimePrefix(prefix) => log
+                .date()
+                .trim_start_matches('[')
+                .replace('T', " ")
+                .starts_with(prefix.as_str()),
+            Predicate::Field(name, op, value) => {
+                let field = self.field(name);
+                match op {
+                    Op::Eq | Op::NotEq => (op == &Op::Eq) == (field == Some(value.as_str())),
+                    _ => field.is_some_and(|f| op.matches(compare_values(f, value))),
+                }
+            }
+            Predicate::FieldRegex(name, op, re) => {
+                (op == &Op::Match) == self.field(name).is_some_and(|f| re.is_match(f))
+            }
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .get_or_init(|| self.log.fields())
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Compare as numbers when both are numbers, otherwise as strings
+fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::log_line::LogData;
+    use crate::raw_parse::RawParser;
+
+    use super::*;
+
+    const SHORT_LOG: &str = "[2023-02-14 13:42:48] local.DEBUG: healthcheck ok [] []
+[2023-02-14 13:43:50] local.WARNING: Incoming webhook: 7 {\"userId\":42} []
+[2023-02-14 13:43:51] production.ERROR: Incoming webhook: 8 {\"userId\":7} []
+[2023-02-14 14:00:00] local.ERROR: Payment failed {\"userId\":42,\"amount\":100.5} {\"ip\":\"::1\"}
+";
+
+    fn matching(query: &str) -> Vec<usize> {
+        let data = LogData::from_content(&RawParser::default(), SHORT_LOG.to_owned());
+        let query = Query::parse(query).unwrap();
+
+        data.iter()
+            .enumerate()
+            .filter(|(_, l)| query.matches(l))
+            .map(|(ix, _)| ix)
+            .collect()
+    }
+
+    fn error(query: &str) -> QueryError {
+        Query::parse(query).unwrap_err()
+    }
+
+    #[test]
+    fn bare_words_are_regex() {
+        assert_eq!(matching("webhook"), vec![1, 2]);
+        assert_eq!(matching("text~\"webhook: [78]\""), vec![1, 2]);
+        assert_eq!(matching("ERROR"), vec![2, 3]);
+    }
+
+    #[test]
+    fn plain_regex_lines() {
+        assert_eq!(matching("webhook: [78]"), vec![1, 2]);
+        assert_eq!(matching("webhook: 7"), vec![1]);
+        assert_eq!(matching("healthcheck ok"), vec![0]);
+        assert_eq!(matching("not found"), Vec::<usize>::new());
+        assert_eq!(matching("Payment (failed)?"), vec![3]);
+        assert_eq!(matching("\\w+: \\d+"), vec![1, 2]);
+        assert_eq!(matching("\\d+:\\d+"), Vec::<usize>::new());
+        assert_eq!(matching("\"userId\":7"), vec![2]);
+        assert!(Query::parse("user <admin>").is_ok());
+        assert_eq!(error("(unclosed").message, "error: unclosed group");
+    }
+
+    #[test]
+    fn php_calls_are_regex() {
+        let log = "[2023-02-14 13:42:48] local.ERROR: at Connection::runQueryCallback() and $user->save() [] []";
+        let data = LogData::from_content(&RawParser::default(), log.to_owned());
+        let line = data.get(0).unwrap();
+
+        for query in [
+            "Connection::runQueryCallback",
+            "Connection::run",
+            "user->save",
+            "r->s",
+            "Message::send|user->save",
+        ] {
+            assert!(Query::parse(query).unwrap().matches(line), "{}", query);
+        }
+        assert!(!Query::parse("Foo::bar").unwrap().matches(line));
+        assert!(!Query::parse("a->b").unwrap().matches(line));
+    }
+
+    #[test]
+    fn level_and_channel() {
+        assert_eq!(matching("level>=warning"), vec![1, 2, 3]);
+        assert_eq!(matching("level:error and channel:local"), vec![3]);
+        assert_eq!(matching("level<warn"), vec![0]);
+        assert_eq!(matching("env!=local"), vec![2]);
+        assert_eq!(matching("channel~^prod"), vec![2]);
+    }
+
+    #[test]
+    fn text_and_not() {
+        assert_eq!(matching("text~\"webhook\" and not text~8"), vec![1]);
+        assert_eq!(matching("message:PAYMENT"), vec![3]);
+        assert_eq!(matching("not text~healthcheck"), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn time_predicates() {
+        assert_eq!(matching("time>\"2023-02-14 13:43\""), vec![1, 2, 3]);
+        assert_eq!(matching("time<=\"2023-02-14 13:43:50\""), vec![0, 1]);
+        assert_eq!(matching("time:\"2023-02-14 14\""), vec![3]);
+    }
+
+    #[test]
+    fn context_fields() {
+        assert_eq!(matching("userId:42"), vec![1, 3]);
+        assert_eq!(matching("userId>10"), vec![1, 3]);
+        assert_eq!(matching("amount>=100"), vec![3]);
+        assert_eq!(matching("extra.ip~\":\""), vec![3]);
+        assert_eq!(matching("userId!=42"), vec![0, 2]);
+    }
+
+    #[test]
+    fn or_and_parens() {
+        assert_eq!(matching("level:debug or userId:7"), vec![0, 2]);
+        assert_eq!(
+            matching("(level:debug or level:error) channel:local"),
+            vec![0, 3]
+        );
+        assert_eq!(
+            matching("level>=warning and channel:local and text~\"webhook\" and time>\"2023-02-14 13:00\" and not text~healthcheck"),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(
+            error("level>=apple"),
+            QueryError {
+                pos: 7,
+                message: "unknown level 'apple'".to_owned()
+            }
+        );
+        assert_eq!(error("text~\"webhook").pos, 5);
+        assert_eq!(error("(level:error").pos, 0);
+        assert_eq!(error("level:error)").pos, 11);
+        assert_eq!(error("level:").pos, 6);
+        assert_eq!(error("time>yesterday").pos, 5);
+        assert_eq!(error("text~\"(a\"").pos, 5);
+        assert_eq!(error("level=>error").pos, 5);
+        assert_eq!(error("level:error and").pos, 15);
+    }
+}
@@ -1,96 +1,59 @@
-use crate::log_line::{LogLine, LogData, LogLines};
+use crate::log_format::{LogFormat, LARAVEL};
+use crate::log_line::{LogChunk, LogLines};
 
+pub struct RawParser {
+    format: &'static dyn LogFormat,
+}
 
-const LARAVEL_DATE: &[u8; 22] =  b"[dddd-dd-dd dd:dd:dd] ";
-
-#[allow(unused_variables, dead_code)]
-// use regex::{Regex};
-pub struct RawParser {}
+impl Default for RawParser {
+    fn default() -> Self {
+        RawParser::new(&LARAVEL)
+    }
+}
 
 impl RawParser {
-    // Return Vec<(start_ix, len)>
-    #[allow(unused_variables, dead_code)]
+    pub fn new(format: &'static dyn LogFormat) -> Self {
+        RawParser { format }
+    }
+
+    /// Return the start index of every log entry in `log_text`
     pub fn parse_lines(&self, log_text: &str) -> Vec<usize> {
         let mut list = Vec::new();
-        let mut prev_newline = true;
 
         let test_arr = log_text.as_bytes();
-        for i in 0..(test_arr.len() - LARAVEL_DATE.len()) {
-            let c = test_arr[i];
-            if prev_newline {
-                let cc = c as char;
-                // println!("Check new char: {cc}");
-
-                let (got_match, offset) = self.match_date(&test_arr[i..]);
-                if got_match {
-                    // println!("Push index: {offset}");
-                    list.push(i);
-                }
-
-                prev_newline = false;
-            } else {
-                let cc = c as char;
-                prev_newline = c == b'\n' || c == b'\r' || i == 0;
-                // println!("Check newline {cc} {prev_newline}");
+        let mut line_start = 0;
+        while line_start < test_arr.len() {
+            if self.format.is_entry_start(&test_arr[line_start..]) {
+                list.push(line_start);
             }
-        }
-
-        return list;        
-    }
 
-    fn match_date(&self, text: &[u8]) -> (bool, usize) {
-        let mut ix = 0;
-        let mut match_iter = LARAVEL_DATE.iter();
-
-        while let Some(mc) = match_iter.next() {
-            let c = text[ix];
-            ix += 1;
-                // println!("In! '{c}'");
-            let res = match mc {
-                // 'n' => 
-                b'd' => c >= 48 && c <= 57,
-                 _ => *mc == c,
-            };
-                 
-
-            if !res {
-                return (false, ix);
+            match test_arr[line_start..].iter().position(|c| *c == b'\n') {
+                Some(pos) => line_start += pos + 1,
+                None => break,
             }
         }
-    
-        return (true, ix);
-    }
-
-    pub fn map_log(&self, log_text: String, log_start: Vec<usize>) -> LogData {
-
-        let sl = [log_start.as_slice(), &[log_text.len()]].concat(); 
-
-        LogData::new(log_text, move |txt| {
-            let mut log_lines = Vec::new();
-            let mut pix = 0;
-            let mut ix = 1;
 
-            while ix < sl.len() {
-                let start = sl[pix];
-                let end = sl[ix];
+        list
+    }
 
-                log_lines.push(LogLine::parse(&txt[start..end]));
+    pub fn map_log(&self, log_text: String, log_start: Vec<usize>) -> LogChunk {
+        let format = self.format;
+        let sl = [log_start.as_slice(), &[log_text.len()]].concat();
 
-                ix+=1;
-                pix+=1;
-            }
-     
-           LogLines(log_lines)
+        LogChunk::new(log_text, move |txt| {
+            let log_lines = sl
+                .windows(2)
+                .map(|w| format.parse_line(&txt[w[0]..w[1]]))
+                .collect();
 
+            LogLines(log_lines)
         })
     }
 }
 
-
-
 #[cfg(test)]
 mod tests {
-    use crate::log_line::LogLine;
+    use crate::log_format::{LogFormat, LARAVEL};
 
     // use crate::{log_line };
     use super::RawParser;
@@ -102,8 +65,8 @@ mod tests {
 [2023-02-14 13:43:50] local.ERROR: Incoming webhook: 9 ";
 
 
-        let p = RawParser {};
-        let lines = p.parse_lines(&short_log);
+        let p = RawParser::default();
+        let lines = p.parse_lines(short_log);
         let log_data = p.map_log(short_log.to_string(), lines);
         
         // First
@@ -180,8 +143,8 @@ mod tests {
     fn map_correct_slug_and_date() {
         let short_log: &str = "[2023-02-14 13:43:49] local.DEBUG: banan ding dong";
 
-        let p = RawParser {};
-        let lines = p.parse_lines(&short_log);
+        let p = RawParser::default();
+        let lines = p.parse_lines(short_log);
         let log_data = p.map_log(short_log.to_string(), lines);
             // LogLine::parse("[2023-02-14 13:43:49]  banan ding dong")
         assert_eq!(
@@ -205,12 +168,12 @@ mod tests {
     fn map_simple_string() {
         let short_log: &str = "[2023-02-14 13:43:49] apple: banan ding dong";
 
-        let p = RawParser {};
-        let lines = p.parse_lines(&short_log);
+        let p = RawParser::default();
+        let lines = p.parse_lines(short_log);
         let log_data = p.map_log(short_log.to_string(), lines);
         assert_eq!(
             log_data.borrow_dependent().0[0],
-            LogLine::parse("[2023-02-14 13:43:49] apple: banan ding dong")
+            LARAVEL.parse_line("[2023-02-14 13:43:49] apple: banan ding dong")
         );
     }
 
@@ -219,9 +182,8 @@ mod tests {
     fn match_date_starty_of_line() {
         let short_log: &str = "[2023-02-14 13:43:49] apple: banan ding dong";
 
-        let p = RawParser {};
         assert_eq!(
-            p.match_date(&short_log.as_bytes()[0..]),
+            LARAVEL.match_date(&short_log.as_bytes()[0..]),
             (true, 22)
         );
     }
@@ -230,9 +192,8 @@ mod tests {
     fn match_date_middle_of_line_no_match() {
         let short_log: &str = "asbc[2023-02-14 13:43:49] apple: banan ding dong";
 
-        let p = RawParser {};
         assert_eq!(
-            p.match_date(short_log.as_bytes()),
+            LARAVEL.match_date(short_log.as_bytes()),
             (false, 1)
         );
     }
@@ -242,9 +203,8 @@ mod tests {
         let short_log: &str = "
 [2023-02-14 13:43:49] apple: banan ding dong";
 
-        let p = RawParser {};
         assert_eq!(
-            p.match_date(short_log.as_bytes()),
+            LARAVEL.match_date(short_log.as_bytes()),
             (false, 1)
         );
     }
@@ -255,7 +215,7 @@ mod tests {
     fn exploration_line() {
         let short_log: &str = "[2023-02-14 13:43:49] apple: banan ding dong";
 
-        let p = RawParser {};
+        let p = RawParser::default();
         assert_eq!(
             p.parse_lines(short_log),
             vec![0]
@@ -270,7 +230,7 @@ mod tests {
 [2023-02-14 13:43:49] local.INFO: Incoming webhook: 8 
 [2023-02-14 13:43:50] local.INFO: Incoming webhook: 9 ";
 
-        let p = RawParser {};
+        let p = RawParser::default();
         assert_eq!(
             p.parse_lines(short_log),
             vec![0, 55, 110]
@@ -284,7 +244,7 @@ mod tests {
 Log line 3
 [2023-02-14 13:43:49] local.INFO: Incoming webhook: 8 ";
 
-        let p = RawParser {};
+        let p = RawParser::default();
         assert_eq!(
             p.parse_lines(short_log),
             vec![0, 81]
@@ -298,7 +258,7 @@ Log line 3
 Log line 3
 [2023-02-14 13:43:49] local.INFO: Incoming webhook: 8 ";
 
-        let p = RawParser {};
+        let p = RawParser::default();
         assert_eq!(
             p.parse_lines(short_log),
             vec![0, 117]
@@ -0,0 +1,143 @@
+use std::ops::Range;
+
+use ratatui::style::{Color, Style};
+use regex::Regex;
+
+use crate::highlight;
+use crate::log_line::LogLine;
+
+pub const MATCH_COLOR: Color = Color::Yellow;
+pub const MATCH_STYLE: Style = Style::new().fg(Color::Black).bg(MATCH_COLOR);
+
+/// Less style search, matching logs stay in the list and are highlighted
+#[derive(Debug, Clone)]
+pub struct Search {
+    /// Pattern as typed by the user
+    text: String,
+    regex: Regex,
+}
+
+impl Search {
+    /// Case insensitive unless the pattern has an upper case letter
+    pub fn new(pattern: &str) -> Result<Search, regex::Error> {
+        let regex = if has_uppercase(pattern) {
+            Regex::new(pattern)?
+        } else {
+            Regex::new(&format!("(?i){}", pattern))?
+        };
+
+        Ok(Search {
+            text: pattern.to_owned(),
+            regex,
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The compiled pattern, including any case flag
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn matches(&self, log: &LogLine) -> bool {
+        self.regex.is_match(log.text())
+    }
+
+    /// Char ranges of the matches in `line`
+    pub fn char_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(line)
+            .filter(|m| !m.is_empty())
+            .map(|m| {
+                let start = line[..m.start()].chars().count();
+                start..start + m.as_str().chars().count()
+            })
+            .collect()
+    }
+
+    /// Row and char column of every match in `lines`, matches never span lines
+    pub fn find_in_lines(&self, lines: &[String]) -> Vec<(usize, usize)> {
+        lines
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                self.char_ranges(line)
+                    .into_iter()
+                    .map(move |range| (row, range.start))
+            })
+            .collect()
+    }
+
+    /// Patch the style of the chars in `text` matching the search
+    pub fn apply(&self, text: &str, styles: &mut [Style]) {
+        for m in self.regex.find_iter(text) {
+            highlight::patch_chars(text, m.range(), MATCH_STYLE, styles);
+        }
+    }
+}
+
+/// Last line of a regex error is the reason, the lines before point out the position
+pub fn regex_error_reason(err: &regex::Error) -> String {
+    let message = err.to_string();
+    message.lines().last().unwrap_or_default().to_owned()
+}
+
+/// Upper case letters outside escapes, `\W` or `\S` don't count
+fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_case() {
+        let search = Search::new("webhook").unwrap();
+        assert!(search.regex.is_match("Incoming WebHook"));
+
+        let search = Search::new("WebHook").unwrap();
+        assert!(!search.regex.is_match("incoming webhook"));
+
+        for pattern in [r"webhook\W", r"webhook\S", r"\Dwebhook", r"\Bebhook"] {
+            let search = Search::new(pattern).unwrap();
+            assert!(search.regex.is_match("Incoming WebHook: 7"), "{}", pattern);
+        }
+        assert!(!Search::new(r"\wWebHook")
+            .unwrap()
+            .regex
+            .is_match(" webhook"));
+
+        assert!(Search::new("(unclosed").is_err());
+    }
+
+    #[test]
+    fn find_hits_in_lines() {
+        let lines = vec!["ärr err".to_owned(), String::new(), "Err".to_owned()];
+        let search = Search::new("err").unwrap();
+
+        assert_eq!(search.find_in_lines(&lines), vec![(0, 4), (2, 0)]);
+    }
+
+    #[test]
+    fn highlight_matches() {
+        let search = Search::new("o+").unwrap();
+        let mut styles = vec![Style::default(); 11];
+        search.apply("foo bar boo", &mut styles);
+
+        assert_eq!(styles[0], Style::default());
+        assert_eq!(&styles[1..3], &[MATCH_STYLE; 2]);
+        assert_eq!(styles[3], Style::default());
+        assert_eq!(styles[10], MATCH_STYLE);
+    }
+}
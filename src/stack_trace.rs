@@ -0,0 +1,248 @@
+/// Exceptions logged by Laravel, outermost first followed by `[previous exception]`s
+#[derive(Debug, PartialEq, Eq)]
+pub struct StackTrace {
+    pub exceptions: Vec<ExceptionTrace>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExceptionTrace {
+    pub class: String,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub frames: Vec<Frame>,
+}
+
+/// A `#0 /var/www/app/Foo.php(123): call()` line
+#[derive(Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub index: usize,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// Frame written as `[internal function]` instead of a file
+    pub internal: bool,
+    pub call: String,
+}
+
+impl Frame {
+    pub fn is_vendor(&self) -> bool {
+        self.file.as_ref().is_some_and(|f| f.contains("/vendor/"))
+    }
+}
+
+impl StackTrace {
+    /// Parse the exception part of a log entry, returns None if there is no stack trace
+    pub fn parse(text: &str) -> Option<StackTrace> {
+        let mut exceptions: Vec<ExceptionTrace> = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim_end();
+            if let Some(header) = line.find("[object] (") {
+                if let Some(exception) = Self::parse_header(&line[header + 10..]) {
+                    exceptions.push(exception);
+                }
+            } else if let Some(frame) = Self::parse_frame(line) {
+                if let Some(exception) = exceptions.last_mut() {
+                    exception.frames.push(frame);
+                }
+            }
+        }
+
+        if exceptions.iter().all(|e| e.frames.is_empty()) {
+            return None;
+        }
+
+        Some(StackTrace { exceptions })
+    }
+
+    /// Parse `Class(code: 0): message at /file.php:12)`
+    fn parse_header(header: &str) -> Option<ExceptionTrace> {
+        let (class, rest) = header.split_once("(code: ")?;
+        let (_code, rest) = rest.split_once("): ")?;
+        let rest = rest.strip_suffix(')').unwrap_or(rest);
+
+        let (message, file, line) = match rest.rsplit_once(" at ") {
+            Some((message, location)) => {
+                let (file, line) = split_location(location, ':');
+                (message, Some(file), line)
+            }
+            None => (rest, None, None),
+        };
+
+        Some(ExceptionTrace {
+            class: class.to_owned(),
+            message: message.to_owned(),
+            file: file.map(str::to_owned),
+            line,
+            frames: Vec::new(),
+        })
+    }
+
+    /// Parse `#0 /file.php(12): call()`, `#1 [internal function]: call()` or `#2 {main}`
+    fn parse_frame(line: &str) -> Option<Frame> {
+        let (index, rest) = line.strip_prefix('#')?.split_once(' ')?;
+        let index = index.parse().ok()?;
+
+        let (file, line, call) = match rest.split_once("): ") {
+            Some((location, call)) if location.ends_with(|c: char| c.is_ascii_digit()) => {
+                let (file, line) = split_location(location, '(');
+                (Some(file.to_owned()), line, call)
+            }
+            _ => (
+                None,
+                None,
+                rest.split_once(": ").map_or(rest, |(_, call)| call),
+            ),
+        };
+
+        Some(Frame {
+            index,
+            file,
+            line,
+            internal: rest.starts_with("[internal function]"),
+            call: call.to_owned(),
+        })
+    }
+}
+
+/// Split `file:12` or `file(12` into file and line
+fn split_location(location: &str, separator: char) -> (&str, Option<u32>) {
+    match location.rsplit_once(separator) {
+        Some((file, line)) if line.parse::<u32>().is_ok() => (file, line.parse().ok()),
+        _ => (location, None),
+    }
+}
+
+/// What a row in the stack trace view shows
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TraceRow {
+    Exception(usize),
+    Frame(usize, usize),
+    /// A run of vendor frames hidden when collapsed, holds the number of frames
+    Collapsed(usize),
+}
+
+impl StackTrace {
+    /// Rows to render, with runs of vendor frames folded into one row when `collapse_vendor`
+    pub fn rows(&self, collapse_vendor: bool) -> Vec<TraceRow> {
+        let mut rows = Vec::new();
+
+        for (ex_ix, exception) in self.exceptions.iter().enumerate() {
+            rows.push(TraceRow::Exception(ex_ix));
+
+            let mut hidden = 0;
+            for (frame_ix, frame) in exception.frames.iter().enumerate() {
+                if collapse_vendor && frame.is_vendor() {
+                    hidden += 1;
+                    continue;
+                }
+
+                if hidden > 0 {
+                    rows.push(TraceRow::Collapsed(hidden));
+                    hidden = 0;
+                }
+                rows.push(TraceRow::Frame(ex_ix, frame_ix));
+            }
+
+            if hidden > 0 {
+                rows.push(TraceRow::Collapsed(hidden));
+            }
+        }
+
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXCEPTION: &str = "[object] (DivisionByZeroError(code: 0): Division by zero at /var/www/html/app/Http/Controllers/Foo.php:12)
+[stacktrace]
+#0 /var/www/html/vendor/laravel/framework/src/Illuminate/Routing/Controller.php(54): App\\Http\\Controllers\\Foo->index()
+#1 /var/www/html/vendor/laravel/framework/src/Illuminate/Routing/ControllerDispatcher.php(43): Illuminate\\Routing\\Controller->callAction('index', Array)
+#2 /var/www/html/app/Http/Middleware/Auth.php(20): Illuminate\\Routing\\ControllerDispatcher->dispatch()
+#3 [internal function]: App\\Http\\Middleware\\Auth->handle()
+#4 {main}
+
+[previous exception] [object] (Exception(code: 3): Inner: at the start at /var/www/html/app/Inner.php:3)
+[stacktrace]
+#0 {main}
+";
+
+    #[test]
+    fn parse_exception_chain() {
+        let trace = StackTrace::parse(EXCEPTION).unwrap();
+
+        assert_eq!(trace.exceptions.len(), 2);
+
+        let outer = &trace.exceptions[0];
+        assert_eq!(outer.class, "DivisionByZeroError");
+        assert_eq!(outer.message, "Division by zero");
+        assert_eq!(
+            outer.file.as_deref(),
+            Some("/var/www/html/app/Http/Controllers/Foo.php")
+        );
+        assert_eq!(outer.line, Some(12));
+        assert_eq!(outer.frames.len(), 5);
+
+        let inner = &trace.exceptions[1];
+        assert_eq!(inner.class, "Exception");
+        assert_eq!(inner.message, "Inner: at the start");
+        assert_eq!(inner.line, Some(3));
+        assert_eq!(inner.frames.len(), 1);
+    }
+
+    #[test]
+    fn parse_frames() {
+        let trace = StackTrace::parse(EXCEPTION).unwrap();
+        let frames = &trace.exceptions[0].frames;
+
+        assert_eq!(
+            frames[2],
+            Frame {
+                index: 2,
+                file: Some("/var/www/html/app/Http/Middleware/Auth.php".to_owned()),
+                line: Some(20),
+                internal: false,
+                call: "Illuminate\\Routing\\ControllerDispatcher->dispatch()".to_owned(),
+            }
+        );
+        assert!(frames[0].is_vendor());
+        assert!(!frames[2].is_vendor());
+
+        assert_eq!(frames[3].file, None);
+        assert!(frames[3].internal);
+        assert_eq!(frames[3].call, "App\\Http\\Middleware\\Auth->handle()");
+        assert_eq!(frames[4].call, "{main}");
+        assert!(!frames[4].internal);
+    }
+
+    #[test]
+    fn no_stack_trace() {
+        assert_eq!(StackTrace::parse("Incoming webhook: 7"), None);
+        assert_eq!(
+            StackTrace::parse("[object] (Exception(code: 0): Oops at /a.php:1)"),
+            None
+        );
+    }
+
+    #[test]
+    fn collapse_vendor_frames() {
+        let trace = StackTrace::parse(EXCEPTION).unwrap();
+
+        assert_eq!(trace.rows(false).len(), 8);
+        assert_eq!(
+            trace.rows(true),
+            vec![
+                TraceRow::Exception(0),
+                TraceRow::Collapsed(2),
+                TraceRow::Frame(0, 2),
+                TraceRow::Frame(0, 3),
+                TraceRow::Frame(0, 4),
+                TraceRow::Exception(1),
+                TraceRow::Frame(1, 0),
+            ]
+        );
+    }
+}
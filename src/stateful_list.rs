@@ -1,10 +1,15 @@
 use ratatui::widgets::ListState;
-use std::{cmp, mem};
 
 use log::trace;
+use std::ops::Range;
 
+use crate::level::{Level, LevelFilter};
 use crate::log_line::LogData;
 use crate::log_line::LogLine;
+use crate::query::Query;
+use crate::raw_parse::RawParser;
+use crate::search::Search;
+use crate::time_range::TimeRange;
 
 pub struct StatefulList {
     /// Keeps track of UI list state
@@ -13,22 +18,50 @@ pub struct StatefulList {
     /// Parsed log data
     items: LogData,
 
+    /// Parser for the log format of the current file
+    parser: RawParser,
+
     /// Filtered and ordered index of logs
     index_list: Vec<usize>,
 
     /// Skip the first <cutoff> logs
     cutoff: usize,
+
+    /// Levels shown in the list
+    levels: LevelFilter,
+
+    /// Show only logs matching any of these
+    filters: Vec<Query>,
+
+    /// Show only logs written in this range
+    time_range: TimeRange,
+
+    /// Highlight logs matching the search, without hiding the others
+    search: Option<Search>,
+
+    /// Positions in `index_list` of logs matching the search
+    search_hits: Vec<usize>,
+
+    /// Positions in `index_list` of logs with level error or worse
+    error_positions: Vec<usize>,
 }
 
 impl StatefulList {
-    pub fn with_items(items: LogData) -> StatefulList {
+    pub fn with_items(parser: RawParser, items: LogData) -> StatefulList {
         let index_list: Vec<usize> = (0..items.len()).collect();
 
         let mut lst = StatefulList {
             state: ListState::default(),
             index_list,
             items,
+            parser,
             cutoff: 0,
+            levels: LevelFilter::default(),
+            filters: Vec::new(),
+            time_range: TimeRange::default(),
+            search: None,
+            search_hits: Vec::new(),
+            error_positions: Vec::new(),
         };
 
         lst.update_ix_list();
@@ -36,31 +69,89 @@ impl StatefulList {
         lst
     }
 
-    /// Rerun filter and cutoff
+    /// Rerun filter and cutoff, keeps the selection on the same log if it is still shown
     fn update_ix_list(&mut self) {
+        let selected_log = self.selected_log_ix();
+
         self.index_list.clear();
-        for (ix, _log) in self.items.log_lines().iter().enumerate().skip(self.cutoff) {
-            self.index_list.push(ix);
+        self.search_hits.clear();
+        self.error_positions.clear();
+        self.extend_ix_list(0);
+
+        if let Some(log_ix) = selected_log {
+            if self.index_list.is_empty() {
+                self.unselect();
+            } else {
+                let ix = self.index_list.partition_point(|ix| *ix < log_ix);
+                self.state.select(Some(ix.min(self.index_list.len() - 1)));
+            }
         }
     }
 
-    /// Changes the current logdata, used when current log file is removed or 
-    /// cleared outside this program
-    pub fn change_log_data(&mut self, log_data: LogData) {
-        let data_len = log_data.len();
-        self.items = log_data;
-        if let Some(state) = self.state.selected() {
-            *self.state.selected_mut() = Some(cmp::min(state, data_len));
+    /// Add the logs from `first_log` and onwards that pass cutoff and filters
+    fn extend_ix_list(&mut self, first_log: usize) {
+        let first_log = first_log.max(self.cutoff);
+        for (ix, log) in self.items.iter().enumerate().skip(first_log) {
+            if self.levels.is_shown(log.level())
+                && (self.time_range.is_empty() || self.time_range.contains(log.timestamp()))
+                && self.matches_filters(log)
+            {
+                if self
+                    .search
+                    .as_ref()
+                    .is_some_and(|search| search.matches(log))
+                {
+                    self.search_hits.push(self.index_list.len());
+                }
+                if log.level().is_some_and(|level| level >= Level::Error) {
+                    self.error_positions.push(self.index_list.len());
+                }
+                self.index_list.push(ix);
+            }
         }
-        self.update_ix_list();
+    }
+
+    fn matches_filters(&self, log: &LogLine) -> bool {
+        self.filters.is_empty() || self.filters.iter().any(|query| query.matches(log))
+    }
+
+    /// Index into the log data of the selected log
+    pub fn selected_log_ix(&self) -> Option<usize> {
+        self.state
+            .selected()
+            .and_then(|ix| self.index_list.get(ix).copied())
     }
 
     /// Add new text to current log data
     pub fn append_text(&mut self, content: &str) {
-        let items = mem::replace(&mut self.items, LogData::empty());
-        self.items = items.append_text(content);
-        // TODO: Consider filtering only new lines rather then all the lines
-        self.update_ix_list();
+        let first_changed = self.items.append_text(&self.parser, content);
+        self.update_from(first_changed);
+    }
+
+    /// Add text from a new file after rotation, the last log of the old file is finished
+    pub fn append_rotated(&mut self, content: &str) {
+        let first_added = self.items.append_chunk(&self.parser, content);
+        self.update_from(first_added);
+    }
+
+    /// Update the shown logs after the logs from `first_changed` were added or parsed again
+    fn update_from(&mut self, first_changed: usize) {
+        // The last log may have been parsed again with the new text
+        let kept = self.index_list.partition_point(|ix| *ix < first_changed);
+        self.index_list.truncate(kept);
+        let kept_hits = self.search_hits.partition_point(|pos| *pos < kept);
+        self.search_hits.truncate(kept_hits);
+        let kept_errors = self.error_positions.partition_point(|pos| *pos < kept);
+        self.error_positions.truncate(kept_errors);
+        self.extend_ix_list(first_changed);
+
+        if let Some(selected) = self.state.selected() {
+            if self.index_list.is_empty() {
+                self.unselect();
+            } else if selected >= self.index_list.len() {
+                self.state.select(Some(self.index_list.len() - 1));
+            }
+        }
     }
 
     pub fn set_cutoff(&mut self, cutoff: usize) {
@@ -69,15 +160,164 @@ impl StatefulList {
         self.update_ix_list();
     }
 
+    pub fn levels(&self) -> &LevelFilter {
+        &self.levels
+    }
+
+    pub fn toggle_level(&mut self, level: Level) {
+        self.levels.toggle(level);
+        self.update_ix_list();
+    }
+
+    pub fn cycle_min_level(&mut self) {
+        self.levels.cycle_min();
+        self.update_ix_list();
+    }
+
+    pub fn show_all_levels(&mut self) {
+        self.levels = LevelFilter::default();
+        self.update_ix_list();
+    }
+
+    /// Replace the filters, an empty list shows all logs
+    pub fn set_filters(&mut self, filters: Vec<Query>) {
+        self.filters = filters;
+        self.update_ix_list();
+    }
+
+    pub fn time_range(&self) -> &TimeRange {
+        &self.time_range
+    }
+
+    pub fn set_time_range(&mut self, time_range: TimeRange) {
+        self.time_range = time_range;
+        self.update_ix_list();
+    }
+
+    pub fn search(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    pub fn set_search(&mut self, search: Option<Search>) {
+        self.search = search;
+        self.search_hits.clear();
+
+        if let Some(search) = &self.search {
+            for (pos, ix) in self.index_list.iter().enumerate() {
+                if self.items.get(*ix).is_some_and(|log| search.matches(log)) {
+                    self.search_hits.push(pos);
+                }
+            }
+        }
+    }
+
+    /// Select the next log matching the search, wraps around to the first
+    pub fn next_search_hit(&mut self) {
+        let hit = match self.state.selected() {
+            Some(selected) => {
+                let next = self.search_hits.partition_point(|pos| *pos <= selected);
+                self.search_hits.get(next).or(self.search_hits.first())
+            }
+            None => self.search_hits.first(),
+        };
+
+        if let Some(pos) = hit {
+            self.state.select(Some(*pos));
+        }
+    }
+
+    /// Select the previous log matching the search, wraps around to the last
+    pub fn previous_search_hit(&mut self) {
+        let hit = match self.state.selected() {
+            Some(selected) => {
+                let previous = self.search_hits.partition_point(|pos| *pos < selected);
+                previous
+                    .checked_sub(1)
+                    .and_then(|ix| self.search_hits.get(ix))
+                    .or(self.search_hits.last())
+            }
+            None => self.search_hits.last(),
+        };
+
+        if let Some(pos) = hit {
+            self.state.select(Some(*pos));
+        }
+    }
+
+    /// Positions of the shown logs matching the search
+    pub fn search_hits(&self) -> &[usize] {
+        &self.search_hits
+    }
+
+    /// Positions of the shown logs with level error or worse
+    pub fn error_positions(&self) -> &[usize] {
+        &self.error_positions
+    }
+
+    /// Number of the selected hit, if the selected log matches, and the total number of hits
+    pub fn search_position(&self) -> (Option<usize>, usize) {
+        let current = self
+            .state
+            .selected()
+            .and_then(|selected| self.search_hits.binary_search(&selected).ok())
+            .map(|ix| ix + 1);
+
+        (current, self.search_hits.len())
+    }
+
+    /// Number of logs before cutoff and filters
+    pub fn total_len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Number of cleared logs
+    pub fn cutoff(&self) -> usize {
+        self.cutoff
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        !self.filters.is_empty() || !self.levels.is_all() || !self.time_range.is_empty()
+    }
+
+    /// Number of logs shown after cutoff and filters
+    pub fn len(&self) -> usize {
+        self.index_list.len()
+    }
+
     pub fn clear_all(&mut self) {
         self.unselect();
         self.set_cutoff(self.items.len());
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &LogLine<'_>> + '_ {
-        self.index_list
+        self.iter_window(0..self.index_list.len())
+    }
+
+    /// Shown logs at the positions in `window`
+    pub fn iter_window(&self, window: Range<usize>) -> impl Iterator<Item = &LogLine<'_>> + '_ {
+        self.index_list[window]
             .iter()
-            .map(|ix| self.items.borrow_dependent().0.get(*ix).unwrap())
+            .map(|ix| self.items.get(*ix).unwrap())
+    }
+
+    /// Positions shown in a list with room for `rows` logs. Scrolls like the ratatui list so
+    /// the selection stays visible, and keeps the list filled when it shrinks.
+    pub fn visible_window(&mut self, rows: usize) -> Range<usize> {
+        let rows = rows.max(1);
+        let len = self.index_list.len();
+
+        let mut offset = self.state.offset();
+        if let Some(selected) = self.state.selected() {
+            if selected < offset {
+                offset = selected;
+            } else if selected >= offset + rows {
+                offset = selected + 1 - rows;
+            }
+        }
+        offset = offset.min(len.saturating_sub(rows));
+
+        *self.state.offset_mut() = offset;
+        offset..(offset + rows).min(len)
     }
 
     pub fn next(&mut self) {
@@ -120,7 +360,7 @@ impl StatefulList {
     }
 
     pub fn goto_start(&mut self) {
-        if self.index_list.len() > 0 {
+        if !self.index_list.is_empty() {
             self.state.select(Some(0));
         } else {
             self.state.select(None);
@@ -128,7 +368,7 @@ impl StatefulList {
     }
 
     pub fn goto_end(&mut self) {
-        if self.index_list.len() > 0 {
+        if !self.index_list.is_empty() {
             self.state.select(Some(self.index_list.len() - 1));
         } else {
             self.state.select(None);
@@ -136,7 +376,7 @@ impl StatefulList {
     }
 
     pub fn jump_relative(&mut self, jump: isize) {
-        if self.index_list.len() > 0 {
+        if !self.index_list.is_empty() {
             let curent_ix = self.state.offset() as isize;
             let ix = (curent_ix + jump).clamp(0, self.index_list.len() as isize - 1);
             self.state.select(Some(ix as usize));
@@ -147,13 +387,169 @@ impl StatefulList {
         self.state.select(None);
     }
 
-    pub fn selected_item(&mut self) -> Option<&LogLine> {
-        let ix = self.state.selected();
-        if let Some(ix) = ix {
-            return Some(&self.items.log_lines()[ix]);
-        }
-
-        None
+    pub fn selected_item(&mut self) -> Option<&LogLine<'_>> {
+        self.selected_log_ix().and_then(|ix| self.items.get(ix))
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT_LOG: &str = "[2023-02-14 13:42:48] local.DEBUG: log1
+[2023-02-14 13:43:50] local.ERROR: log2
+[2023-02-14 13:43:51] local.INFO: log3
+[2023-02-14 13:43:52] local.CRITICAL: log4
+";
+
+    fn make_list() -> StatefulList {
+        let parser = RawParser::default();
+        let data = LogData::from_content(&parser, SHORT_LOG.to_owned());
+        StatefulList::with_items(parser, data)
+    }
+
+    fn texts(list: &StatefulList) -> Vec<&str> {
+        list.iter().map(|l| l.text().trim_end()).collect()
+    }
+
+    #[test]
+    fn filter_on_min_level() {
+        let mut list = make_list();
+        list.levels.set_min(Level::Error);
+        list.update_ix_list();
+
+        assert_eq!(texts(&list), vec!["log2", "log4"]);
+
+        list.show_all_levels();
+        assert_eq!(list.len(), 4);
+    }
+
+    #[test]
+    fn selection_follows_log_when_filtering() {
+        let mut list = make_list();
+        list.state.select(Some(3));
+
+        list.toggle_level(Level::Info);
+        assert_eq!(list.state.selected(), Some(2));
+        assert_eq!(list.selected_item().unwrap().text(), "log4\n");
+
+        // Selected log is hidden, select the next one shown instead
+        list.state.select(Some(0));
+        list.toggle_level(Level::Debug);
+        assert_eq!(list.selected_item().unwrap().text(), "log2\n");
+    }
+
+    #[test]
+    fn filter_on_regex() {
+        let mut list = make_list();
+
+        list.set_filters(vec![Query::parse("log[13]").unwrap()]);
+        assert_eq!(texts(&list), vec!["log1", "log3"]);
+
+        // Any filter matching shows the log, level is matched too
+        list.set_filters(vec![
+            Query::parse("^log1").unwrap(),
+            Query::parse("CRITICAL").unwrap(),
+        ]);
+        assert_eq!(texts(&list), vec!["log1", "log4"]);
+
+        list.toggle_level(Level::Debug);
+        assert_eq!(texts(&list), vec!["log4"]);
+
+        list.set_filters(Vec::new());
+        assert_eq!(list.len(), 3);
+    }
+
+    #[test]
+    fn filter_on_time_range() {
+        let mut list = make_list();
+        let now = chrono::Local::now();
+
+        list.set_time_range(
+            TimeRange::parse("2023-02-14 13:43:50..2023-02-14 13:43:51", now).unwrap(),
+        );
+        assert_eq!(texts(&list), vec!["log2", "log3"]);
+
+        list.set_time_range(TimeRange::default());
+        assert_eq!(list.len(), 4);
+    }
+
+    #[test]
+    fn filter_on_query() {
+        let mut list = make_list();
+
+        list.set_filters(vec![Query::parse("level>=error and not text~4").unwrap()]);
+        assert_eq!(texts(&list), vec!["log2"]);
+    }
+
+    #[test]
+    fn filter_appended_logs() {
+        let mut list = make_list();
+        list.set_filters(vec![Query::parse("level>=error").unwrap()]);
+        list.goto_end();
+
+        list.append_text("[2023-02-14 13:43:53] local.ERROR: log5 part");
+        assert_eq!(texts(&list), vec!["log2", "log4", "log5 part"]);
+
+        // Rest of the last log arrives in the next read
+        list.append_text(" two\n[2023-02-14 13:43:54] local.INFO: log6\n");
+        assert_eq!(texts(&list), vec!["log2", "log4", "log5 part two"]);
+        assert_eq!(list.selected_item().unwrap().text(), "log4\n");
+    }
+
+    #[test]
+    fn search_hits_keep_all_logs_visible() {
+        let mut list = make_list();
+        list.set_search(Some(Search::new("log[24]").unwrap()));
+
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.search_position(), (None, 2));
+
+        list.next_search_hit();
+        assert_eq!(list.selected_item().unwrap().text(), "log2\n");
+        assert_eq!(list.search_position(), (Some(1), 2));
+
+        list.next_search_hit();
+        assert_eq!(list.selected_item().unwrap().text(), "log4\n");
+
+        list.next_search_hit();
+        assert_eq!(list.selected_item().unwrap().text(), "log2\n");
+
+        list.previous_search_hit();
+        assert_eq!(list.selected_item().unwrap().text(), "log4\n");
+
+        // Hits follow the filters and new logs
+        list.toggle_level(Level::Error);
+        assert_eq!(list.search_position(), (Some(1), 1));
+
+        list.append_text("[2023-02-14 13:43:53] local.INFO: log42\n");
+        assert_eq!(list.search_position(), (Some(1), 2));
+
+        assert_eq!(list.error_positions(), &[2]);
+
+        list.set_search(None);
+        assert_eq!(list.search_position(), (None, 0));
+    }
+
+    #[test]
+    fn window_follows_selection() {
+        let mut list = make_list();
+        assert_eq!(list.visible_window(2), 0..2);
+
+        list.goto_end();
+        assert_eq!(list.visible_window(2), 2..4);
+        assert_eq!(list.state.offset(), 2);
+
+        list.goto_start();
+        assert_eq!(list.visible_window(3), 0..3);
+
+        // Filtering shrinks the list under the offset
+        list.goto_end();
+        list.visible_window(1);
+        list.set_filters(vec![Query::parse("log1").unwrap()]);
+        assert_eq!(list.visible_window(2), 0..1);
+
+        let texts: Vec<_> = list.iter_window(0..1).map(|l| l.text()).collect();
+        assert_eq!(texts, vec!["log1\n"]);
+    }
+}
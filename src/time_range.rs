@@ -0,0 +1,314 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+use crate::timestamp::{parse_user_time, Timestamp};
+
+/// One end of a time range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Typed by the user, compared with the time as written in the log
+    Wall(NaiveDateTime),
+    /// Relative to now, compared with the log in local time unless the log has an offset
+    Instant(DateTime<Utc>),
+}
+
+impl Bound {
+    /// Compare the bound with a log time, returns the ordering of the log relative to the bound
+    fn cmp_log(&self, ts: &Timestamp) -> Ordering {
+        match (self, ts.offset) {
+            (Bound::Wall(time), _) => ts.local.cmp(time),
+            (Bound::Instant(instant), Some(offset)) => {
+                let log_time = offset.from_local_datetime(&ts.local).single();
+                log_time.map_or(Ordering::Equal, |t| t.cmp(&instant.fixed_offset()))
+            }
+            (Bound::Instant(instant), None) => {
+                ts.local.cmp(&instant.with_timezone(&Local).naive_local())
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        let time = match self {
+            Bound::Wall(time) => *time,
+            Bound::Instant(instant) => instant.with_timezone(&Local).naive_local(),
+        };
+        time.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+/// Show only logs written between `since` and `until`. Logs without a time are always shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeRange {
+    pub since: Option<Bound>,
+    pub until: Option<Bound>,
+}
+
+impl TimeRange {
+    pub fn contains(&self, timestamp: Option<Timestamp>) -> bool {
+        let Some(ts) = timestamp else {
+            return true;
+        };
+
+        self.since.is_none_or(|b| b.cmp_log(&ts).is_ge())
+            && self.until.is_none_or(|b| b.cmp_log(&ts).is_le())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    pub fn describe(&self) -> String {
+        match (&self.since, &self.until) {
+            (Some(since), Some(until)) => format!("{}..{}", since.describe(), until.describe()),
+            (Some(since), None) => format!("since {}", since.describe()),
+            (None, Some(until)) => format!("until {}", until.describe()),
+            (None, None) => "all time".to_owned(),
+        }
+    }
+
+    /// Parse `15m`, `last 2 hours`, `A..B`, `between A and B`, `since A until B` or `until B`
+    pub fn parse(expr: &str, now: DateTime<Local>) -> Result<TimeRange, String> {
+        let expr = expr.trim();
+        let lower = expr.to_ascii_lowercase();
+
+        let range = |since: &str, until: &str| -> Result<TimeRange, String> {
+            let since = parse_optional_bound(since, now)?;
+            // `2023-02-14 13:00..14:00` ends on the same day as it starts
+            let until = match (&since, parse_time_of_day(until)) {
+                (Some(Bound::Wall(start)), Some(time)) => {
+                    Some(Bound::Wall(start.date().and_time(time)))
+                }
+                _ => parse_optional_bound(until, now)?,
+            };
+
+            Ok(TimeRange { since, until })
+        };
+
+        if let Some((since, until)) = expr.split_once("..") {
+            return range(since, until);
+        }
+
+        for (start, separator) in [
+            ("between ", " and "),
+            ("since ", " until "),
+            ("from ", " to "),
+        ] {
+            if let Some(rest) = lower.strip_prefix(start) {
+                let rest_ix = start.len();
+                return match rest.find(separator) {
+                    Some(sep_ix) => range(
+                        &expr[rest_ix..rest_ix + sep_ix],
+                        &expr[rest_ix + sep_ix + separator.len()..],
+                    ),
+                    None => range(&expr[rest_ix..], ""),
+                };
+            }
+        }
+
+        if let Some(rest) = lower.strip_prefix("until ") {
+            return range("", &expr[lower.len() - rest.len()..]);
+        }
+
+        range(expr, "")
+    }
+}
+
+fn parse_optional_bound(text: &str, now: DateTime<Local>) -> Result<Option<Bound>, String> {
+    if text.trim().is_empty() {
+        Ok(None)
+    } else {
+        parse_bound(text, now).map(Some)
+    }
+}
+
+/// Parse a time without a date like `14:00` or `14:00:30`
+fn parse_time_of_day(text: &str) -> Option<NaiveTime> {
+    let text = text.trim();
+    NaiveTime::parse_from_str(text, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M"))
+        .ok()
+}
+
+/// Parse an absolute time, `now`, `today`, `yesterday` or a duration before now like `15m`
+pub fn parse_bound(text: &str, now: DateTime<Local>) -> Result<Bound, String> {
+    let text = text.trim();
+    let midnight = |days_ago: i64| {
+        let date = now.date_naive() - Duration::days(days_ago);
+        Bound::Wall(date.and_hms_opt(0, 0, 0).unwrap_or_default())
+    };
+
+    match text.to_ascii_lowercase().as_str() {
+        "now" => return Ok(Bound::Instant(now.to_utc())),
+        "today" => return Ok(midnight(0)),
+        "yesterday" => return Ok(midnight(1)),
+        _ => {}
+    }
+
+    if let Some(time) = parse_user_time(text) {
+        return Ok(Bound::Wall(time));
+    }
+
+    match parse_duration(text) {
+        Some(duration) => Ok(Bound::Instant((now - duration).to_utc())),
+        None => Err(format!("can't parse time '{}'", text)),
+    }
+}
+
+/// Parse `15m`, `1h30m`, `2 days`, `last 15 minutes` or `15 minutes ago`
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim().to_ascii_lowercase();
+    let text = text.strip_prefix("last ").unwrap_or(&text);
+    let text = text.strip_prefix("past ").unwrap_or(text);
+    let text = text.strip_suffix(" ago").unwrap_or(text).trim();
+
+    let mut total = Duration::zero();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: i64 = rest[..digits].parse().ok()?;
+
+        rest = rest[digits..].trim_start();
+        let unit_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "s" | "sec" | "secs" | "second" | "seconds" => Duration::seconds(1),
+            "m" | "min" | "mins" | "minute" | "minutes" => Duration::minutes(1),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Duration::hours(1),
+            "d" | "day" | "days" => Duration::days(1),
+            "w" | "week" | "weeks" => Duration::weeks(1),
+            _ => return None,
+        };
+
+        let amount = i32::try_from(amount).ok()?;
+        total = total.checked_add(&unit.checked_mul(amount)?)?;
+        rest = rest[unit_len..].trim_start();
+    }
+
+    if total.is_zero() {
+        None
+    } else {
+        Some(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn naive(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 2, 14)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    fn now() -> DateTime<Local> {
+        Local.from_local_datetime(&naive(14, 0)).unwrap()
+    }
+
+    fn ts(text: &str) -> Option<Timestamp> {
+        Timestamp::parse(text)
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(
+            parse_duration("last 15 minutes"),
+            Some(Duration::minutes(15))
+        );
+        assert_eq!(parse_duration("2 days ago"), Some(Duration::days(2)));
+        assert_eq!(parse_duration("15"), None);
+        assert_eq!(parse_duration("apple"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn last_minutes() {
+        let range = TimeRange::parse("last 15 minutes", now()).unwrap();
+
+        assert!(range.contains(ts("2023-02-14 13:50:00")));
+        assert!(!range.contains(ts("2023-02-14 13:44:59")));
+        assert!(range.contains(None));
+        assert_eq!(range.until, None);
+    }
+
+    #[test]
+    fn between_absolute_times() {
+        let range = TimeRange::parse("between 2023-02-14 13:00 and 2023-02-14 13:30", now());
+        let range = range.unwrap();
+
+        assert_eq!(range.since, Some(Bound::Wall(naive(13, 0))));
+        assert_eq!(range.until, Some(Bound::Wall(naive(13, 30))));
+        assert!(range.contains(ts("2023-02-14 13:30:00")));
+        assert!(!range.contains(ts("2023-02-14 13:30:01")));
+
+        assert_eq!(
+            TimeRange::parse("2023-02-14 13:00..2023-02-14 13:30", now()),
+            Ok(range)
+        );
+    }
+
+    #[test]
+    fn time_only_until_uses_since_date() {
+        let range = TimeRange::parse("2023-02-14 13:00..14:00", now()).unwrap();
+        assert_eq!(range.since, Some(Bound::Wall(naive(13, 0))));
+        assert_eq!(range.until, Some(Bound::Wall(naive(14, 0))));
+
+        let range = TimeRange::parse("between 2023-02-14 13:00 and 13:30:00", now()).unwrap();
+        assert_eq!(range.until, Some(Bound::Wall(naive(13, 30))));
+
+        // Nothing to take the date from
+        assert!(TimeRange::parse("1h..14:00", now()).is_err());
+    }
+
+    #[test]
+    fn open_ranges() {
+        let range = TimeRange::parse("until 2023-02-14 13:00", now()).unwrap();
+        assert_eq!(range.since, None);
+        assert_eq!(range.until, Some(Bound::Wall(naive(13, 0))));
+
+        let range = TimeRange::parse("since 1h", now()).unwrap();
+        assert!(range.contains(ts("2023-02-14 13:30:00")));
+        assert!(!range.contains(ts("2023-02-14 12:30:00")));
+
+        let range = TimeRange::parse("2023-02-14 13:00..", now()).unwrap();
+        assert_eq!(range.until, None);
+
+        let range = TimeRange::parse("today", now()).unwrap();
+        assert_eq!(range.since, Some(Bound::Wall(naive(0, 0))));
+    }
+
+    #[test]
+    fn relative_with_offset() {
+        let range = TimeRange::parse("30m", now()).unwrap();
+        let Some(Bound::Instant(since)) = range.since else {
+            panic!("Expected relative bound");
+        };
+
+        // Same instant written in another offset
+        let at = since
+            .fixed_offset()
+            .with_timezone(&chrono::FixedOffset::east_opt(5 * 3600).unwrap());
+        let text = at.format("%Y-%m-%dT%H:%M:%S%:z").to_string();
+        assert!(range.contains(ts(&text)));
+
+        let before = (at - Duration::seconds(1))
+            .format("%Y-%m-%dT%H:%M:%S%:z")
+            .to_string();
+        assert!(!range.contains(ts(&before)));
+    }
+
+    #[test]
+    fn invalid_range() {
+        assert!(TimeRange::parse("apple", now()).is_err());
+        assert!(TimeRange::parse("between 1h and apple", now()).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+
+/// When a log entry was written. The offset is only known when the log includes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+    /// Date and time as written in the log
+    pub local: NaiveDateTime,
+    pub offset: Option<FixedOffset>,
+}
+
+const NAIVE_FORMATS: [&str; 2] = ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"];
+
+/// Formats accepted when the user types a time, seconds and time are optional
+const USER_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// Parse a time typed by the user like `2023-02-14 13:00` or `2023-02-14`
+pub fn parse_user_time(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+
+    USER_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+impl Timestamp {
+    /// Parse `2023-02-14 13:42:48` or ISO-8601 like `2024-05-01T12:00:00.123456+00:00`
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let text = text.trim_matches(|c| c == '[' || c == ']').trim();
+
+        if let Some(local) = Self::parse_plain(text) {
+            return Some(Timestamp {
+                local,
+                offset: None,
+            });
+        }
+
+        if let Some(datetime) = Self::parse_with_offset(text) {
+            return Some(Timestamp {
+                local: datetime.naive_local(),
+                offset: Some(*datetime.offset()),
+            });
+        }
+
+        NAIVE_FORMATS
+            .iter()
+            .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+            .map(|local| Timestamp {
+                local,
+                offset: None,
+            })
+    }
+
+    /// The common `2023-02-14 13:42:48` layout without going through chrono's format parser
+    fn parse_plain(text: &str) -> Option<NaiveDateTime> {
+        let bytes = text.as_bytes();
+        let layout = b"dddd-dd-dd dd:dd:dd";
+        let fits = bytes.len() == layout.len()
+            && bytes.iter().zip(layout).all(|(c, l)| match l {
+                b'd' => c.is_ascii_digit(),
+                b' ' => matches!(c, b' ' | b'T'),
+                _ => c == l,
+            });
+        if !fits {
+            return None;
+        }
+
+        let number = |start: usize, len: usize| {
+            bytes[start..start + len]
+                .iter()
+                .fold(0, |n, c| n * 10 + u32::from(c - b'0'))
+        };
+
+        NaiveDate::from_ymd_opt(number(0, 4) as i32, number(5, 2), number(8, 2))?.and_hms_opt(
+            number(11, 2),
+            number(14, 2),
+            number(17, 2),
+        )
+    }
+
+    fn parse_with_offset(text: &str) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(text)
+            .or_else(|_| DateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f%z"))
+            .ok()
+    }
+}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    /// Ordered by the time written in the log, offsets only break ties
+    fn cmp(&self, other: &Self) -> Ordering {
+        let offset = |t: &Timestamp| t.offset.map(|o| o.local_minus_utc());
+        self.local
+            .cmp(&other.local)
+            .then_with(|| offset(self).cmp(&offset(other)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use super::*;
+
+    fn naive(h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2023, 2, 14)
+            .unwrap()
+            .and_hms_opt(h, m, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_laravel_date() {
+        assert_eq!(
+            Timestamp::parse("[2023-02-14 13:42:48]"),
+            Some(Timestamp {
+                local: naive(13, 42, 48),
+                offset: None
+            })
+        );
+    }
+
+    #[test]
+    fn parse_iso_with_offset_and_fraction() {
+        let ts = Timestamp::parse("[2023-02-14T13:42:48.123456+02:00]").unwrap();
+
+        assert_eq!(ts.local.with_nanosecond(0), Some(naive(13, 42, 48)));
+        assert_eq!(ts.local.nanosecond(), 123_456_000);
+        assert_eq!(ts.offset, FixedOffset::east_opt(2 * 3600));
+
+        let ts = Timestamp::parse("2023-02-14T13:42:48+0000").unwrap();
+        assert_eq!(ts.offset, FixedOffset::east_opt(0));
+
+        let ts = Timestamp::parse("2023-02-14T13:42:48Z").unwrap();
+        assert_eq!(ts.offset, FixedOffset::east_opt(0));
+    }
+
+    #[test]
+    fn parse_iso_without_offset() {
+        let ts = Timestamp::parse("2023-02-14T13:42:48.5").unwrap();
+
+        assert_eq!(ts.local.second(), 48);
+        assert_eq!(ts.offset, None);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(Timestamp::parse(""), None);
+        assert_eq!(Timestamp::parse("[2023-02-30 13:42:48]"), None);
+        assert_eq!(Timestamp::parse("[2023-02-14 13:42:4x]"), None);
+        assert_eq!(Timestamp::parse("[2023-02-14 13-42:48]"), None);
+        assert_eq!(Timestamp::parse("[2023-02-14 25:42:48]"), None);
+    }
+
+    #[test]
+    fn parse_times_from_user() {
+        assert_eq!(parse_user_time("2023-02-14 13:42"), Some(naive(13, 42, 0)));
+        assert_eq!(
+            parse_user_time(" 2023-02-14T13:42:48 "),
+            Some(naive(13, 42, 48))
+        );
+        assert_eq!(parse_user_time("2023-02-14"), Some(naive(0, 0, 0)));
+        assert_eq!(parse_user_time("13:42"), None);
+    }
+
+    #[test]
+    fn order_by_log_time() {
+        let a = Timestamp::parse("2023-02-14 13:42:48").unwrap();
+        let b = Timestamp::parse("2023-02-14T13:42:49.1+02:00").unwrap();
+
+        assert!(a < b);
+    }
+}
@@ -0,0 +1,166 @@
+use ratatui::{prelude::*, widgets::*};
+
+use crate::editor::FileRef;
+use crate::stack_trace::{StackTrace, TraceRow};
+
+/// Stack trace of the selected log shown in the content pane
+pub struct TraceView {
+    trace: StackTrace,
+    rows: Vec<TraceRow>,
+    collapse_vendor: bool,
+    state: ListState,
+}
+
+impl TraceView {
+    pub fn new(trace: StackTrace) -> Self {
+        let collapse_vendor = true;
+        TraceView {
+            rows: trace.rows(collapse_vendor),
+            trace,
+            collapse_vendor,
+            state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    pub fn toggle_vendor(&mut self) {
+        // Keep the selection on the same frame when possible
+        let selected = self.selected_row();
+
+        self.collapse_vendor = !self.collapse_vendor;
+        self.rows = self.trace.rows(self.collapse_vendor);
+
+        let ix = selected
+            .and_then(|row| self.rows.iter().position(|r| *r == row))
+            .unwrap_or(0);
+        self.state.select(Some(ix));
+    }
+
+    pub fn selected_row(&self) -> Option<TraceRow> {
+        self.state
+            .selected()
+            .and_then(|ix| self.rows.get(ix).copied())
+    }
+
+    /// File and line of the selected frame or exception
+    pub fn selected_file_ref(&self) -> Option<FileRef> {
+        let (file, line) = match self.selected_row()? {
+            TraceRow::Exception(ex_ix) => {
+                let exception = &self.trace.exceptions[ex_ix];
+                (exception.file.as_ref()?, exception.line?)
+            }
+            TraceRow::Frame(ex_ix, frame_ix) => {
+                let frame = &self.trace.exceptions[ex_ix].frames[frame_ix];
+                (frame.file.as_ref()?, frame.line?)
+            }
+            TraceRow::Collapsed(_) => return None,
+        };
+
+        Some(FileRef {
+            path: file.clone(),
+            line,
+        })
+    }
+
+    pub fn next(&mut self) {
+        self.jump_relative(1);
+    }
+
+    pub fn previous(&mut self) {
+        self.jump_relative(-1);
+    }
+
+    pub fn jump_relative(&mut self, jump: isize) {
+        let ix = self.state.selected().unwrap_or(0) as isize + jump;
+        let ix = ix.clamp(0, self.rows.len() as isize - 1);
+        self.state.select(Some(ix as usize));
+    }
+
+    pub fn goto_start(&mut self) {
+        self.state.select(Some(0));
+    }
+
+    pub fn goto_end(&mut self) {
+        self.state.select(Some(self.rows.len() - 1));
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, block: Block) {
+        let mut state = self.state.clone();
+        let items: Vec<ListItem> = self.rows.iter().map(|row| self.render_row(*row)).collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, area, &mut state);
+        self.state = state;
+    }
+
+    fn render_row(&self, row: TraceRow) -> ListItem<'_> {
+        match row {
+            TraceRow::Exception(ex_ix) => {
+                let exception = &self.trace.exceptions[ex_ix];
+                let title = if ex_ix == 0 {
+                    exception.class.clone()
+                } else {
+                    format!("Previous: {}", exception.class)
+                };
+
+                let mut lines = vec![
+                    Line::from(Span::styled(
+                        title,
+                        Style::default()
+                            .fg(Color::LightRed)
+                            .add_modifier(Modifier::BOLD),
+                    )),
+                    Line::from(exception.message.as_str()),
+                ];
+                if let Some(file) = &exception.file {
+                    lines.push(Line::from(format!(
+                        "at {}:{}",
+                        file,
+                        exception.line.unwrap_or_default()
+                    )));
+                }
+
+                ListItem::new(lines)
+            }
+            TraceRow::Frame(ex_ix, frame_ix) => {
+                let frame = &self.trace.exceptions[ex_ix].frames[frame_ix];
+                let style = if frame.is_vendor() || frame.file.is_none() {
+                    Style::default().fg(Color::Gray)
+                } else {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                };
+
+                let location = match (&frame.file, frame.line) {
+                    (Some(file), Some(line)) => format!("{}({})", file, line),
+                    (Some(file), None) => file.clone(),
+                    _ if frame.internal => "[internal function]".to_owned(),
+                    // Like `#12 {main}`, the call is all there is
+                    _ => {
+                        return ListItem::new(Line::from(Span::styled(
+                            format!("#{} {}", frame.index, frame.call),
+                            style,
+                        )))
+                    }
+                };
+
+                ListItem::new(vec![
+                    Line::from(Span::styled(
+                        format!("#{} {}", frame.index, location),
+                        style,
+                    )),
+                    Line::from(format!("    {}", frame.call)),
+                ])
+            }
+            TraceRow::Collapsed(count) => ListItem::new(Line::from(Span::styled(
+                format!("  ... {} vendor frames", count),
+                Style::default()
+                    .fg(Color::Gray)
+                    .add_modifier(Modifier::ITALIC),
+            ))),
+        }
+    }
+}
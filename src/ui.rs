@@ -1,4 +1,10 @@
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ratatui::layout::{Constraint, Direction, Layout, Margin, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Scrollbar, ScrollbarOrientation, ScrollbarState};
+use ratatui::Frame;
 
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -34,3 +40,115 @@ pub fn make_title(title: &str, has_focus: bool) -> String {
         format!("|  {}  |", title)
     }
 }
+
+/// Copy to the system clipboard with the OSC 52 escape sequence, works over ssh too
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+/// Color of the marker on each of `height` rows. `markers` are sorted positions in `0..len`,
+/// earlier marker lists win when they share a row.
+pub fn marker_rows(height: usize, len: usize, markers: &[(&[usize], Color)]) -> Vec<Option<Color>> {
+    (0..height)
+        .map(|row| {
+            let start = row * len / height;
+            let end = ((row + 1) * len / height).max(start + 1);
+
+            markers.iter().find_map(|(positions, color)| {
+                let ix = positions.partition_point(|pos| *pos < start);
+                positions.get(ix).filter(|pos| **pos < end).map(|_| *color)
+            })
+        })
+        .collect()
+}
+
+/// Scrollbar on the right border of `area` with markers for interesting positions
+pub fn render_scrollbar(
+    f: &mut Frame,
+    area: Rect,
+    len: usize,
+    position: usize,
+    markers: &[(&[usize], Color)],
+) {
+    let track = area.inner(Margin {
+        vertical: 1,
+        horizontal: 0,
+    });
+    if track.width == 0 || track.height == 0 || len == 0 {
+        return;
+    }
+
+    let mut state = ScrollbarState::new(len).position(position);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None);
+    f.render_stateful_widget(scrollbar, track, &mut state);
+
+    let x = track.right() - 1;
+    let rows = marker_rows(track.height as usize, len, markers);
+    for (row, color) in rows.into_iter().enumerate() {
+        if let Some(color) = color {
+            f.buffer_mut()[(x, track.y + row as u16)]
+                .set_symbol("■")
+                .set_style(Style::default().fg(color));
+        }
+    }
+}
+
+/// Human readable file size like `12.3 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_on_scrollbar_rows() {
+        let errors = [0, 50, 51, 99];
+        let hits = [51];
+        let rows = marker_rows(10, 100, &[(&hits, Color::Yellow), (&errors, Color::Red)]);
+
+        assert_eq!(rows[0], Some(Color::Red));
+        assert_eq!(rows[1], None);
+        assert_eq!(rows[5], Some(Color::Yellow));
+        assert_eq!(rows[9], Some(Color::Red));
+
+        // More rows than positions
+        let rows = marker_rows(4, 2, &[(&[1], Color::Red)]);
+        assert_eq!(rows, vec![None, None, Some(Color::Red), Some(Color::Red)]);
+    }
+
+    #[test]
+    fn scrollbar_on_zero_width_area() {
+        let backend = ratatui::backend::TestBackend::new(10, 10);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| render_scrollbar(f, Rect::new(0, 0, 0, 10), 100, 5, &[(&[1], Color::Red)]))
+            .unwrap();
+    }
+
+    #[test]
+    fn human_readable_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(500 * 1024 * 1024), "500.0 MB");
+    }
+}
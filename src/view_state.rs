@@ -0,0 +1,231 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use log::error;
+use ratatui::layout::{Constraint, Layout, Rect};
+use serde::{Deserialize, Serialize};
+
+/// How the list and content panes share the screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaneLayout {
+    #[default]
+    SideBySide,
+    Stacked,
+    ListOnly,
+    ContentOnly,
+}
+
+impl PaneLayout {
+    pub fn next(self) -> Self {
+        match self {
+            PaneLayout::SideBySide => PaneLayout::Stacked,
+            PaneLayout::Stacked => PaneLayout::ListOnly,
+            PaneLayout::ListOnly => PaneLayout::ContentOnly,
+            PaneLayout::ContentOnly => PaneLayout::SideBySide,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaneLayout::SideBySide => "side by side",
+            PaneLayout::Stacked => "stacked",
+            PaneLayout::ListOnly => "list only",
+            PaneLayout::ContentOnly => "content only",
+        }
+    }
+}
+
+/// View settings remembered between runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    pub layout: PaneLayout,
+    /// Percent of the screen used by the list when both panes are shown
+    pub list_percent: u16,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        ViewState {
+            layout: PaneLayout::default(),
+            list_percent: 50,
+        }
+    }
+}
+
+const MIN_PERCENT: u16 = 10;
+const MAX_PERCENT: u16 = 90;
+const PERCENT_STEP: u16 = 5;
+
+impl ViewState {
+    /// `$XDG_STATE_HOME/termilog/state.toml`, defaults to `~/.local/state`
+    pub fn default_path() -> Option<PathBuf> {
+        let state_dir = match env::var_os("XDG_STATE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?)
+                .join(".local")
+                .join("state"),
+        };
+
+        Some(state_dir.join("termilog").join("state.toml"))
+    }
+
+    /// Load the state from `path`, a missing or broken file gives the defaults
+    pub fn load(path: &Path) -> ViewState {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    error!("Failed to read {}: {}", path.display(), err);
+                }
+                return ViewState::default();
+            }
+        };
+
+        toml::from_str(&content).unwrap_or_else(|err| {
+            error!("Failed to parse {}: {}", path.display(), err);
+            ViewState::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    pub fn cycle_layout(&mut self) {
+        self.layout = self.layout.next();
+    }
+
+    /// Both panes are shown, so the split can be resized
+    pub fn is_split(&self) -> bool {
+        matches!(self.layout, PaneLayout::SideBySide | PaneLayout::Stacked)
+    }
+
+    /// The content pane is shown, so it can take focus
+    pub fn shows_content(&self) -> bool {
+        self.layout != PaneLayout::ListOnly
+    }
+
+    /// Does nothing unless both panes are shown
+    pub fn grow_list(&mut self) {
+        if self.is_split() {
+            self.list_percent = (self.list_percent + PERCENT_STEP).min(MAX_PERCENT);
+        }
+    }
+
+    /// Does nothing unless both panes are shown
+    pub fn shrink_list(&mut self) {
+        if self.is_split() {
+            self.list_percent = self
+                .list_percent
+                .saturating_sub(PERCENT_STEP)
+                .max(MIN_PERCENT);
+        }
+    }
+
+    /// Areas of the list and content panes, a hidden pane has no area
+    pub fn areas(&self, area: Rect) -> (Option<Rect>, Option<Rect>) {
+        let percent = self.list_percent.clamp(MIN_PERCENT, MAX_PERCENT);
+        let constraints = [
+            Constraint::Percentage(percent),
+            Constraint::Percentage(100 - percent),
+        ];
+
+        match self.layout {
+            PaneLayout::SideBySide => {
+                let [list, content] = Layout::horizontal(constraints).areas(area);
+                (Some(list), Some(content))
+            }
+            PaneLayout::Stacked => {
+                let [list, content] = Layout::vertical(constraints).areas(area);
+                (Some(list), Some(content))
+            }
+            PaneLayout::ListOnly => (Some(area), None),
+            PaneLayout::ContentOnly => (None, Some(area)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_within_limits() {
+        let mut view = ViewState::default();
+        for _ in 0..20 {
+            view.grow_list();
+        }
+        assert_eq!(view.list_percent, MAX_PERCENT);
+
+        for _ in 0..20 {
+            view.shrink_list();
+        }
+        assert_eq!(view.list_percent, MIN_PERCENT);
+    }
+
+    #[test]
+    fn no_resize_with_one_pane() {
+        for layout in [PaneLayout::ListOnly, PaneLayout::ContentOnly] {
+            let mut view = ViewState {
+                layout,
+                list_percent: 50,
+            };
+            view.grow_list();
+            view.shrink_list();
+            view.shrink_list();
+            assert_eq!(view.list_percent, 50);
+        }
+    }
+
+    #[test]
+    fn pane_areas() {
+        let area = Rect::new(0, 0, 100, 40);
+        let mut view = ViewState {
+            layout: PaneLayout::SideBySide,
+            list_percent: 30,
+        };
+
+        let (list, content) = view.areas(area);
+        assert_eq!(list, Some(Rect::new(0, 0, 30, 40)));
+        assert_eq!(content, Some(Rect::new(30, 0, 70, 40)));
+
+        view.cycle_layout();
+        let (list, content) = view.areas(area);
+        assert_eq!(list, Some(Rect::new(0, 0, 100, 12)));
+        assert_eq!(content, Some(Rect::new(0, 12, 100, 28)));
+
+        view.cycle_layout();
+        assert_eq!(view.areas(area), (Some(area), None));
+
+        view.cycle_layout();
+        assert_eq!(view.areas(area), (None, Some(area)));
+
+        view.cycle_layout();
+        assert_eq!(view.layout, PaneLayout::SideBySide);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let view = ViewState {
+            layout: PaneLayout::ContentOnly,
+            list_percent: 35,
+        };
+        let content = toml::to_string(&view).unwrap();
+
+        assert_eq!(content, "layout = \"content_only\"\nlist_percent = 35\n");
+        assert_eq!(toml::from_str::<ViewState>(&content).unwrap(), view);
+        assert_eq!(
+            toml::from_str::<ViewState>("").unwrap(),
+            ViewState::default()
+        );
+    }
+}
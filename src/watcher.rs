@@ -0,0 +1,432 @@
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::mpsc::{self, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use log::{error, info, trace};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::events::AppEvent;
+
+/// How often the file is checked when polling
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Check now and then even with notifications, some mounts never send them
+const NOTIFY_FALLBACK: Duration = Duration::from_secs(2);
+
+/// Change to the followed log file
+#[derive(Debug, PartialEq, Eq)]
+pub enum FileEvent {
+    /// New text at the end of the file
+    Appended { text: String, size: u64 },
+    /// Following a new file at the path, or the same one from the start after it was
+    /// truncated. `text` is its content so far.
+    Rotated { text: String, size: u64 },
+    /// The file was removed
+    Missing,
+    /// The file is back after being removed
+    Found,
+}
+
+/// Device and inode, tells a recreated file from the one we have open
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<FileId> {
+    None
+}
+
+/// Follows the log file at a path like `tail -F`, also when it is rotated.
+/// Reads through an open handle so the rest of a rotated file can still be read.
+pub struct FileWatcher {
+    path: PathBuf,
+    /// The file being followed, opened on the first check
+    file: Option<File>,
+    id: Option<FileId>,
+    /// Bytes read so far
+    size: u64,
+    missing: bool,
+}
+
+impl FileWatcher {
+    /// Follow `path`, the first `size` bytes have already been read
+    pub fn new(path: impl Into<PathBuf>, size: u64) -> Self {
+        FileWatcher {
+            path: path.into(),
+            file: None,
+            id: None,
+            size,
+            missing: false,
+        }
+    }
+
+    /// Check the file once, returns what changed since the last check
+    pub fn check(&mut self) -> io::Result<Vec<FileEvent>> {
+        let mut events = Vec::new();
+
+        let meta = match fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                if !self.missing {
+                    self.drain(&mut events)?;
+                    self.file = None;
+                    self.missing = true;
+                    events.push(FileEvent::Missing);
+                }
+                return Ok(events);
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Nothing changes until the file at the path could be opened
+        if self.missing {
+            let opened = self.open()?;
+            self.missing = false;
+            events.push(FileEvent::Found);
+            self.follow(opened, &mut events)?;
+        } else if self.file.is_none() {
+            let (file, id) = self.open()?;
+            self.file = Some(file);
+            self.id = id;
+            self.read_new(&mut events, meta.len())?;
+        } else if file_id(&meta) != self.id {
+            trace!("File replaced by a new one");
+            let opened = self.open()?;
+            self.drain(&mut events)?;
+            self.follow(opened, &mut events)?;
+        } else if meta.len() < self.size {
+            trace!("File truncated {:?} to {:?}", self.size, meta.len());
+            let opened = self.open()?;
+            self.follow(opened, &mut events)?;
+        } else {
+            self.read_new(&mut events, meta.len())?;
+        }
+
+        Ok(events)
+    }
+
+    /// Open the file at the path, the one being followed is kept until `follow` is called
+    fn open(&self) -> io::Result<(File, Option<FileId>)> {
+        let file = File::open(&self.path)?;
+        let id = file_id(&file.metadata()?);
+
+        Ok((file, id))
+    }
+
+    /// Follow a file from `open` from its start
+    fn follow(
+        &mut self,
+        (file, id): (File, Option<FileId>),
+        events: &mut Vec<FileEvent>,
+    ) -> io::Result<()> {
+        self.file = Some(file);
+        self.id = id;
+        self.size = 0;
+        let text = self.read_to_end()?;
+        events.push(FileEvent::Rotated {
+            text,
+            size: self.size,
+        });
+
+        Ok(())
+    }
+
+    /// Read what was added if the file grew to `len`
+    fn read_new(&mut self, events: &mut Vec<FileEvent>, len: u64) -> io::Result<()> {
+        if len > self.size {
+            self.drain(events)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read the rest of the open file
+    fn drain(&mut self, events: &mut Vec<FileEvent>) -> io::Result<()> {
+        let text = self.read_to_end()?;
+        if !text.is_empty() {
+            events.push(FileEvent::Appended {
+                text,
+                size: self.size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Read from `size` to the end of the open file and move `size` past what was read.
+    /// A char cut off at the end is left for the next read.
+    fn read_to_end(&mut self) -> io::Result<String> {
+        let Some(file) = &mut self.file else {
+            return Ok(String::new());
+        };
+        file.seek(SeekFrom::Start(self.size))?;
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let (text, used) = decode_utf8(&bytes);
+        self.size += used as u64;
+
+        Ok(text)
+    }
+
+    /// Check the file whenever it changes and send the changes until the receiver is gone.
+    /// Polls when `poll` is set or filesystem notifications are not available.
+    pub fn spawn(mut self, tx: Sender<AppEvent>, poll: bool) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let (notify_tx, notify_rx) = mpsc::channel();
+            let mut watcher = if poll {
+                None
+            } else {
+                notify_watcher(notify_tx)
+                    .map_err(|err| error!("Failed to watch files, polling: {}", err))
+                    .ok()
+            };
+            let mut watching = false;
+
+            loop {
+                match self.check() {
+                    Ok(events) => {
+                        // The watch stays with the old file, watch the new one at the path
+                        if events
+                            .iter()
+                            .any(|event| matches!(event, FileEvent::Rotated { .. }))
+                        {
+                            watching = false;
+                        }
+
+                        for event in events {
+                            if tx.send(AppEvent::File(event)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(err) => error!("Failed to read {}: {}", self.path.display(), err),
+                }
+
+                // A watch ends with the file, watch again once it is back
+                if let Some(watcher) = &mut watcher {
+                    if self.missing {
+                        watching = false;
+                    } else if !watching {
+                        let _ = watcher.unwatch(&self.path);
+                        match watcher.watch(&self.path, RecursiveMode::NonRecursive) {
+                            Ok(()) => {
+                                info!("Watching {} for changes", self.path.display());
+                                watching = true;
+                            }
+                            Err(err) => error!("Failed to watch {}: {}", self.path.display(), err),
+                        }
+                    }
+                }
+
+                if watching {
+                    let _ = notify_rx.recv_timeout(NOTIFY_FALLBACK);
+                    // One check covers all notifications so far
+                    while notify_rx.try_recv().is_ok() {}
+                } else {
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        })
+    }
+}
+
+/// Watcher that sends to `tx` on every change of a watched path
+fn notify_watcher(tx: Sender<()>) -> notify::Result<RecommendedWatcher> {
+    notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+        Ok(_) => {
+            let _ = tx.send(());
+        }
+        Err(err) => error!("File watch error: {}", err),
+    })
+}
+
+/// Decode `bytes` as UTF-8, returns the text and the number of bytes used.
+/// An incomplete char at the end is not used, invalid bytes are replaced.
+pub fn decode_utf8(bytes: &[u8]) -> (String, usize) {
+    let used = bytes.len() - incomplete_tail(bytes);
+    (String::from_utf8_lossy(&bytes[..used]).into_owned(), used)
+}
+
+/// Length of a char cut off at the end of `bytes`
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    for len in 1..=bytes.len().min(3) {
+        let width = match bytes[bytes.len() - len] {
+            0x80..=0xBF => continue,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return 0,
+        };
+        return if width > len { len } else { 0 };
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn temp_log(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("termilog-{}-{}.log", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn append(path: &PathBuf, bytes: &[u8]) {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    fn appended(text: &str, size: u64) -> FileEvent {
+        FileEvent::Appended {
+            text: text.to_owned(),
+            size,
+        }
+    }
+
+    fn rotated(text: &str, size: u64) -> FileEvent {
+        FileEvent::Rotated {
+            text: text.to_owned(),
+            size,
+        }
+    }
+
+    #[test]
+    fn append_truncate_and_remove() {
+        let path = temp_log("watch");
+        append(&path, b"first\n");
+        let mut watcher = FileWatcher::new(&path, 6);
+        assert_eq!(watcher.check().unwrap(), vec![]);
+
+        append(&path, b"second\n");
+        assert_eq!(watcher.check().unwrap(), vec![appended("second\n", 13)]);
+
+        // copytruncate
+        fs::write(&path, b"new\n").unwrap();
+        assert_eq!(watcher.check().unwrap(), vec![rotated("new\n", 4)]);
+
+        // The rest is read before the file is gone
+        append(&path, b"last\n");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            watcher.check().unwrap(),
+            vec![appended("last\n", 9), FileEvent::Missing]
+        );
+        assert_eq!(watcher.check().unwrap(), vec![]);
+
+        append(&path, b"back\n");
+        assert_eq!(
+            watcher.check().unwrap(),
+            vec![FileEvent::Found, rotated("back\n", 5)]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn follow_renamed_and_recreated() {
+        let path = temp_log("rotate");
+        let rotated_path = path.with_extension("log.1");
+        append(&path, b"old\n");
+        let mut watcher = FileWatcher::new(&path, 4);
+        assert_eq!(watcher.check().unwrap(), vec![]);
+
+        // Same size as the old file, only the inode tells them apart
+        append(&path, b"tail\n");
+        fs::rename(&path, &rotated_path).unwrap();
+        append(&path, b"new\n");
+        append(&path, b"next\n");
+        assert_eq!(
+            watcher.check().unwrap(),
+            vec![appended("tail\n", 9), rotated("new\nnext\n", 9)]
+        );
+
+        append(&rotated_path, b"late\n");
+        append(&path, b"more\n");
+        assert_eq!(watcher.check().unwrap(), vec![appended("more\n", 14)]);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated_path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn found_after_failed_open() {
+        let path = temp_log("reopen");
+        append(&path, b"old line\n");
+        let mut watcher = FileWatcher::new(&path, 9);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(watcher.check().unwrap(), vec![FileEvent::Missing]);
+
+        // Something at the path that can't be opened as a file
+        let socket = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(watcher.check().is_err());
+        drop(socket);
+        fs::remove_file(&path).unwrap();
+
+        append(&path, b"back\n");
+        assert_eq!(
+            watcher.check().unwrap(),
+            vec![FileEvent::Found, rotated("back\n", 5)]
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn char_split_between_reads() {
+        let path = temp_log("utf8");
+        let mut watcher = FileWatcher::new(&path, 0);
+
+        append(&path, "ok ä".as_bytes().split_last().unwrap().1);
+        assert_eq!(watcher.check().unwrap(), vec![appended("ok ", 3)]);
+
+        append(&path, &"ä".as_bytes()[1..]);
+        assert_eq!(watcher.check().unwrap(), vec![appended("ä", 5)]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn spawned_watcher_sends_appends() {
+        let path = temp_log("notify");
+        append(&path, b"first\n");
+
+        let (tx, rx) = mpsc::channel();
+        FileWatcher::new(&path, 6).spawn(tx, false);
+        append(&path, b"second\n");
+
+        // A write before the watch is set up is only seen by the fallback check
+        match rx.recv_timeout(NOTIFY_FALLBACK * 5) {
+            Ok(AppEvent::File(event)) => assert_eq!(event, appended("second\n", 13)),
+            other => panic!("expected an append, got {:?}", other),
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn decode_invalid_bytes() {
+        assert_eq!(decode_utf8(b"a\xffb"), ("a\u{fffd}b".to_owned(), 3));
+        assert_eq!(decode_utf8(b"a\xff\xc3"), ("a\u{fffd}".to_owned(), 2));
+        assert_eq!(decode_utf8(b""), (String::new(), 0));
+    }
+}